@@ -0,0 +1,301 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{Name, Error, WireReader, WireWriter};
+use super::prelude::*;
+
+/// EDNS Client Subnet (RFC 7871)
+#[derive(Clone)]
+pub struct ClientSubnet {
+    pub source_prefix: u8,
+    pub scope_prefix: u8,
+    pub address: IpAddr
+}
+
+/// DNS Cookie (RFC 7873)
+#[derive(Clone)]
+pub struct Cookie {
+    pub client: [u8; 8],
+    /// Empty, or between 8 and 32 octets
+    pub server: Vec<u8>
+}
+
+/// Extended DNS Error (RFC 8914)
+#[derive(Clone)]
+pub struct ExtendedError {
+    pub info_code: u16,
+    pub extra_text: String
+}
+
+/// A single option from the RDATA of an OPT record (RFC 6891)
+///
+/// Options that are malformed or that would not serialize back to exactly
+/// the same octets are kept as `Unknown`, so parsing never loses data.
+#[derive(Clone)]
+pub enum EdnsOption {
+    /// Name server identifier (RFC 5001)
+    Nsid(Vec<u8>),
+    /// DNSSEC algorithms understood (RFC 6975)
+    Dau(Vec<u8>),
+    /// DS hash algorithms understood (RFC 6975)
+    Dhu(Vec<u8>),
+    /// NSEC3 hash algorithms understood (RFC 6975)
+    N3u(Vec<u8>),
+    ClientSubnet(ClientSubnet),
+    /// SOA expire timer (RFC 7314), empty in queries
+    Expire(Option<u32>),
+    Cookie(Cookie),
+    /// Idle timeout in units of 100ms (RFC 7828), empty in queries
+    TcpKeepalive(Option<u16>),
+    /// Number of zero octets of padding (RFC 7830)
+    Padding(u16),
+    /// Closest trust point (RFC 7901)
+    Chain(Name),
+    ExtendedError(ExtendedError),
+    Unknown(u16, Vec<u8>)
+}
+
+pub const NSID: u16 = 3;
+pub const DAU: u16 = 5;
+pub const DHU: u16 = 6;
+pub const N3U: u16 = 7;
+pub const CLIENT_SUBNET: u16 = 8;
+pub const EXPIRE: u16 = 9;
+pub const COOKIE: u16 = 10;
+pub const TCP_KEEPALIVE: u16 = 11;
+pub const PADDING: u16 = 12;
+pub const CHAIN: u16 = 13;
+pub const EXTENDED_ERROR: u16 = 15;
+
+impl EdnsOption {
+    pub fn code(&self) -> u16 {
+        use self::EdnsOption::*;
+        match *self {
+            Nsid(_) => NSID,
+            Dau(_) => DAU,
+            Dhu(_) => DHU,
+            N3u(_) => N3U,
+            ClientSubnet(_) => CLIENT_SUBNET,
+            Expire(_) => EXPIRE,
+            Cookie(_) => COOKIE,
+            TcpKeepalive(_) => TCP_KEEPALIVE,
+            Padding(_) => PADDING,
+            Chain(_) => CHAIN,
+            ExtendedError(_) => EXTENDED_ERROR,
+            Unknown(code, _) => code
+        }
+    }
+    /// Decode the option data for `code`.  This never fails: anything that
+    /// cannot be represented exactly falls back to `Unknown`.
+    pub fn parse(code: u16, data: &[u8]) -> EdnsOption {
+        if let Some(opt) = Self::parse_known(code, data) {
+            let mut check = Vec::new();
+            if opt.serialize_data(&mut check).is_ok() && &check[..] == data {
+                return opt;
+            }
+        }
+        EdnsOption::Unknown(code, data.to_vec())
+    }
+    fn parse_known(code: u16, data: &[u8]) -> Option<EdnsOption> {
+        let mut r = WireReader::new(data);
+        Some(match code {
+            NSID => EdnsOption::Nsid(data.to_vec()),
+            DAU => EdnsOption::Dau(data.to_vec()),
+            DHU => EdnsOption::Dhu(data.to_vec()),
+            N3U => EdnsOption::N3u(data.to_vec()),
+            CLIENT_SUBNET => {
+                let family = r.read_u16().ok()?;
+                let source_prefix = r.read_u8().ok()?;
+                let scope_prefix = r.read_u8().ok()?;
+                let addr = &data[4..];
+                let address = match family {
+                    1 if addr.len() <= 4 => {
+                        let mut octets = [0u8; 4];
+                        octets[..addr.len()].copy_from_slice(addr);
+                        IpAddr::V4(Ipv4Addr::from(octets))
+                    }
+                    2 if addr.len() <= 16 => {
+                        let mut octets = [0u8; 16];
+                        octets[..addr.len()].copy_from_slice(addr);
+                        IpAddr::V6(Ipv6Addr::from(octets))
+                    }
+                    _ => return None
+                };
+                EdnsOption::ClientSubnet(ClientSubnet {
+                    source_prefix,
+                    scope_prefix,
+                    address
+                })
+            }
+            EXPIRE if data.is_empty() => EdnsOption::Expire(None),
+            EXPIRE => EdnsOption::Expire(Some(r.read_u32().ok()?)),
+            COOKIE => {
+                if data.len() < 8 { return None; }
+                let mut client = [0u8; 8];
+                client.copy_from_slice(&data[..8]);
+                EdnsOption::Cookie(Cookie { client, server: data[8..].to_vec() })
+            }
+            TCP_KEEPALIVE if data.is_empty() => EdnsOption::TcpKeepalive(None),
+            TCP_KEEPALIVE => EdnsOption::TcpKeepalive(Some(r.read_u16().ok()?)),
+            PADDING => EdnsOption::Padding(data.len() as u16),
+            CHAIN => EdnsOption::Chain(Name::parse(&mut r).ok()?),
+            EXTENDED_ERROR => {
+                let info_code = r.read_u16().ok()?;
+                let text = String::from_utf8(data[2..].to_vec()).ok()?;
+                EdnsOption::ExtendedError(ExtendedError {
+                    info_code,
+                    extra_text: text
+                })
+            }
+            _ => return None
+        })
+    }
+    /// Write the option data (without the code and length) to `buf`
+    pub fn serialize_data(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        use self::EdnsOption::*;
+        match *self {
+            Nsid(ref d) | Dau(ref d) | Dhu(ref d) | N3u(ref d) | Unknown(_, ref d) => {
+                buf.extend_from_slice(&d[..]);
+            }
+            ClientSubnet(ref ecs) => {
+                let (family, octets, max) = match ecs.address {
+                    IpAddr::V4(a) => (1, a.octets().to_vec(), 32),
+                    IpAddr::V6(a) => (2, a.octets().to_vec(), 128)
+                };
+                if ecs.source_prefix > max || ecs.scope_prefix > max {
+                    return Err(Error::InvalidEdnsOption);
+                }
+                buf.write_u16(family)?;
+                buf.write_u8(ecs.source_prefix)?;
+                buf.write_u8(ecs.scope_prefix)?;
+                //only as many octets as the prefix covers, with the bits
+                //past the prefix cleared
+                let len = (ecs.source_prefix as usize).div_ceil(8);
+                buf.extend_from_slice(&octets[..len]);
+                if ecs.source_prefix % 8 != 0 {
+                    let last = buf.len() - 1;
+                    buf[last] &= 0xFFu8 << (8 - ecs.source_prefix % 8);
+                }
+            }
+            Expire(x) => if let Some(x) = x { buf.write_u32(x)?; },
+            Cookie(ref c) => {
+                if !c.server.is_empty() && (c.server.len() < 8 || c.server.len() > 32) {
+                    return Err(Error::InvalidEdnsOption);
+                }
+                buf.extend_from_slice(&c.client[..]);
+                buf.extend_from_slice(&c.server[..]);
+            }
+            TcpKeepalive(x) => if let Some(x) = x { buf.write_u16(x)?; },
+            Padding(len) => buf.resize(buf.len() + len as usize, 0),
+            Chain(ref n) => {
+                n.serialize(buf)?;
+            }
+            ExtendedError(ref e) => {
+                buf.write_u16(e.info_code)?;
+                buf.extend_from_slice(e.extra_text.as_bytes());
+            }
+        }
+        Ok(())
+    }
+    /// Write the option code, length and data to `buf`
+    pub fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let mut data = Vec::new();
+        self.serialize_data(&mut data)?;
+        if data.len() > 0xFFFF {
+            return Err(Error::InvalidEdnsOption);
+        }
+        buf.write_u16(self.code())?;
+        buf.write_u16(data.len() as u16)?;
+        buf.extend_from_slice(&data[..]);
+        Ok(())
+    }
+}
+
+/// Iterator over the options in an OPT record's data
+pub struct EdnsOptions<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> EdnsOptions<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        EdnsOptions { data }
+    }
+    /// Split off the next option as its code and raw data
+    pub fn next_raw(&mut self) -> Option<Result<(u16, &'a [u8]), Error>> {
+        if self.data.is_empty() {
+            return None;
+        }
+        if self.data.len() < 4 {
+            self.data = &[];
+            return Some(Err(Error::InvalidEdnsOption));
+        }
+        let code = (self.data[0] as u16) << 8 | self.data[1] as u16;
+        let len = ((self.data[2] as usize) << 8 | self.data[3] as usize) + 4;
+        if self.data.len() < len {
+            self.data = &[];
+            return Some(Err(Error::InvalidEdnsOption));
+        }
+        let opt = &self.data[4..len];
+        self.data = &self.data[len..];
+        Some(Ok((code, opt)))
+    }
+}
+
+impl<'a> Iterator for EdnsOptions<'a> {
+    type Item = Result<EdnsOption, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw().map(|r| r.map(|(code, data)| EdnsOption::parse(code, data)))
+    }
+}
+
+fn fmt_hex(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    for byte in data {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+fn fmt_algorithms(f: &mut fmt::Formatter, name: &str, algs: &[u8]) -> fmt::Result {
+    write!(f, "{}:", name)?;
+    for alg in algs {
+        write!(f, " {}", alg)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::EdnsOption::*;
+        match *self {
+            Nsid(ref d) => {
+                write!(f, "NSID: ")?;
+                fmt_hex(f, &d[..])?;
+                if d.iter().all(|c| c.is_ascii_graphic() || *c == b' ') {
+                    write!(f, " (\"{}\")", String::from_utf8_lossy(&d[..]))?;
+                }
+                Ok(())
+            }
+            Dau(ref d) => fmt_algorithms(f, "DAU", &d[..]),
+            Dhu(ref d) => fmt_algorithms(f, "DHU", &d[..]),
+            N3u(ref d) => fmt_algorithms(f, "N3U", &d[..]),
+            ClientSubnet(ref ecs) => write!(f, "CLIENT-SUBNET: {}/{}/{}",
+                ecs.address, ecs.source_prefix, ecs.scope_prefix),
+            Expire(None) => write!(f, "EXPIRE"),
+            Expire(Some(x)) => write!(f, "EXPIRE: {}", x),
+            Cookie(ref c) => {
+                write!(f, "COOKIE: ")?;
+                fmt_hex(f, &c.client[..])?;
+                fmt_hex(f, &c.server[..])
+            }
+            TcpKeepalive(None) => write!(f, "TCP-KEEPALIVE"),
+            TcpKeepalive(Some(x)) => write!(f, "TCP-KEEPALIVE: {}", x),
+            Padding(len) => write!(f, "PADDING: {} octets", len),
+            Chain(ref n) => write!(f, "CHAIN: {}", n),
+            ExtendedError(ref e) => write!(f, "EDE: {} \"{}\"", e.info_code, e.extra_text),
+            Unknown(code, ref d) => {
+                write!(f, "OPT{}: ", code)?;
+                fmt_hex(f, &d[..])
+            }
+        }
+    }
+}
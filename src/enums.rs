@@ -1,3 +1,8 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::Error;
+
 /// The QTYPE value according to RFC 1035
 ///
 /// All "EXPERIMENTAL" markers here are from the RFC
@@ -113,6 +118,53 @@ impl From<u16> for Type {
     }
 }
 
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::All => write!(f, "ANY"),
+            Type::Unknown(x) => write!(f, "TYPE{}", x),
+            ref t => write!(f, "{:?}", t)
+        }
+    }
+}
+
+impl FromStr for Type {
+    type Err = Error;
+    /// Parse a type mnemonic, or the RFC 3597 `TYPEnnn` form
+    fn from_str(s: &str) -> Result<Self, Error> {
+        use self::Type::*;
+        let upper = s.to_ascii_uppercase();
+        Ok(match &upper[..] {
+            "A" => A,
+            "NS" => NS,
+            "MF" => MF,
+            "CNAME" => CNAME,
+            "SOA" => SOA,
+            "MB" => MB,
+            "MG" => MG,
+            "MR" => MR,
+            "NULL" => NULL,
+            "WKS" => WKS,
+            "PTR" => PTR,
+            "HINFO" => HINFO,
+            "MINFO" => MINFO,
+            "MX" => MX,
+            "TXT" => TXT,
+            "AAAA" => AAAA,
+            "SRV" => SRV,
+            "OPT" => OPT,
+            "AXFR" => AXFR,
+            "MAILB" => MAILB,
+            "MAILA" => MAILA,
+            "ANY" | "*" => All,
+            x if x.starts_with("TYPE") => {
+                Type::from(x[4..].parse::<u16>().map_err(|_| Error::InvalidMnemonic)?)
+            }
+            _ => return Err(Error::InvalidMnemonic)
+        })
+    }
+}
+
 /// The QCLASS value according to RFC 1035
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Class {
@@ -159,16 +211,73 @@ impl Into<u16> for Class {
     }
 }
 
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Class::Any => write!(f, "ANY"),
+            Class::Unknown(x) => write!(f, "CLASS{}", x),
+            ref c => write!(f, "{:?}", c)
+        }
+    }
+}
+
+impl FromStr for Class {
+    type Err = Error;
+    /// Parse a class mnemonic, or the RFC 3597 `CLASSnnn` form
+    fn from_str(s: &str) -> Result<Self, Error> {
+        use self::Class::*;
+        let upper = s.to_ascii_uppercase();
+        Ok(match &upper[..] {
+            "IN" => IN,
+            "CS" => CS,
+            "CH" => CH,
+            "HS" => HS,
+            "ANY" | "*" => Any,
+            x if x.starts_with("CLASS") => {
+                Class::from(x[5..].parse::<u16>().map_err(|_| Error::InvalidMnemonic)?)
+            }
+            _ => return Err(Error::InvalidMnemonic)
+        })
+    }
+}
+
 /// The OPCODE value according to RFC 1035
+///
+/// Only the low 4 bits fit in the header, so `Reserved` values above 15
+/// are rejected when a message is serialized.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Opcode {
     StandardQuery,
     InverseQuery,
     ServerStatusRequest,
+    /// Zone change notification (RFC 1996)
+    Notify,
+    /// Dynamic update (RFC 2136)
+    Update,
+    /// DNS Stateful Operations (RFC 8490)
+    Dso,
     Reserved(u16),
 }
 
-/// The RCODE value according to RFC 1035
+impl Opcode {
+    /// Convert a header opcode, rejecting values that don't fit in 4 bits
+    pub fn from_code(code: u16) -> Result<Opcode, Error> {
+        if code > 0b1111 {
+            return Err(Error::InvalidOpcode(code));
+        }
+        Ok(Opcode::from(code))
+    }
+    /// The 4-bit value to put in the header
+    pub fn to_code(self) -> Result<u16, Error> {
+        let code : u16 = self.into();
+        if code > 0b1111 {
+            return Err(Error::InvalidOpcode(code));
+        }
+        Ok(code)
+    }
+}
+
+/// The RCODE value according to RFC 1035, extended to 12 bits by EDNS(0)
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ResponseCode {
     NoError,
@@ -181,6 +290,25 @@ pub enum ResponseCode {
     XRRSet,
     NotAuth,
     NotZone,
+    /// Bad OPT version (RFC 6891)
+    BadVers,
+    /// TSIG signature failure (RFC 8945).  It shares code 16 with BadVers,
+    /// so it is written as 16 but always read back as BadVers.
+    BadSig,
+    /// Key not recognized (RFC 8945)
+    BadKey,
+    /// Signature out of time window (RFC 8945)
+    BadTime,
+    /// Bad TKEY mode (RFC 2930)
+    BadMode,
+    /// Duplicate key name (RFC 2930)
+    BadName,
+    /// Algorithm not supported (RFC 2930)
+    BadAlg,
+    /// Bad truncation (RFC 8945)
+    BadTrunc,
+    /// Bad or missing server cookie (RFC 7873)
+    BadCookie,
     Reserved(u16)
 }
 
@@ -191,6 +319,9 @@ impl From<u16> for Opcode {
             0 => StandardQuery,
             1 => InverseQuery,
             2 => ServerStatusRequest,
+            4 => Notify,
+            5 => Update,
+            6 => Dso,
             x => Reserved(x),
         }
     }
@@ -202,11 +333,25 @@ impl Into<u16> for Opcode {
             StandardQuery => 0,
             InverseQuery => 1,
             ServerStatusRequest => 2,
+            Notify => 4,
+            Update => 5,
+            Dso => 6,
             Reserved(x) => x,
         }
     }
 }
 
+impl ResponseCode {
+    /// The 12-bit value to split between the header and the OPT record
+    pub fn to_code(self) -> Result<u16, Error> {
+        let code : u16 = self.into();
+        if code > 0xFFF {
+            return Err(Error::InvalidResponseCode(code));
+        }
+        Ok(code)
+    }
+}
+
 impl From<u16> for ResponseCode {
     fn from(code: u16) -> ResponseCode {
         use self::ResponseCode::*;
@@ -221,8 +366,15 @@ impl From<u16> for ResponseCode {
             7       => XRRSet,
             8       => NotAuth,
             9       => NotZone,
-            10...15 => Reserved(code),
-            x => panic!("Invalid response code {}", x),
+            16      => BadVers,
+            17      => BadKey,
+            18      => BadTime,
+            19      => BadMode,
+            20      => BadName,
+            21      => BadAlg,
+            22      => BadTrunc,
+            23      => BadCookie,
+            x       => Reserved(x),
         }
     }
 }
@@ -240,6 +392,15 @@ impl Into<u16> for ResponseCode {
             XRRSet         => 7,
             NotAuth        => 8,
             NotZone        => 9,
+            BadVers        => 16,
+            BadSig         => 16, //one way, 16 reads as BadVers
+            BadKey         => 17,
+            BadTime        => 18,
+            BadMode        => 19,
+            BadName        => 20,
+            BadAlg         => 21,
+            BadTrunc       => 22,
+            BadCookie      => 23,
             Reserved(code) => code,
         }
     }
@@ -0,0 +1,272 @@
+//! Internationalized domain names (RFC 5890, RFC 5891)
+//!
+//! Conversion between U-labels and `xn--` A-labels, with the UTS #46
+//! mapping: case folding, width and compatibility mappings and NFC, then
+//! the validity checks including the contextual (RFC 5892 appendix A) and
+//! bidi (RFC 5893) rules.  Processing is nontransitional, so `ß` and `ς`
+//! are kept.  The Unicode tables come from the `idna` crate; punycode
+//! (RFC 3492) is implemented here.  ASCII labels are only lowercased, so
+//! names such as `_tcp` or `r3---sn` pass through.  An invalid label, or an
+//! A-label that doesn't decode to a valid one, gives `InvalidIdna`.
+
+use std::char;
+use std::str::FromStr;
+
+use uts46::uts46::{Uts46, AsciiDenyList, Hyphens, DnsLength};
+
+use super::{Name, Error};
+use super::prelude::*;
+
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+const ACE_PREFIX: &str = "xn--";
+
+fn adapt(delta: u32, num_points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + (BASE - TMIN + 1) * delta / (delta + SKEW)
+}
+
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        TMIN
+    }
+    else if k >= bias + TMAX {
+        TMAX
+    }
+    else {
+        k - bias
+    }
+}
+
+fn encode_digit(d: u32) -> char {
+    if d < 26 {
+        (b'a' + d as u8) as char
+    }
+    else {
+        (b'0' + (d - 26) as u8) as char
+    }
+}
+
+fn decode_digit(c: char) -> Option<u32> {
+    match c {
+        'a'..='z' => Some(c as u32 - 'a' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 26),
+        _ => None
+    }
+}
+
+/// Encode a string with punycode, without the `xn--` prefix
+pub fn punycode_encode(input: &str) -> Result<String, Error> {
+    let input : Vec<u32> = input.chars().map(|c| c as u32).collect();
+    let mut output : String = input.iter().filter(|&&c| c < 0x80)
+        .map(|&c| c as u8 as char).collect();
+    let basic = output.len() as u32;
+    let mut handled = basic;
+    if basic > 0 {
+        output.push('-');
+    }
+    let mut n = INITIAL_N;
+    let mut delta = 0u32;
+    let mut bias = INITIAL_BIAS;
+    while (handled as usize) < input.len() {
+        let m = input.iter().cloned().filter(|&c| c >= n).min().ok_or(Error::InvalidIdna)?;
+        delta = (m - n).checked_mul(handled + 1)
+            .and_then(|x| delta.checked_add(x))
+            .ok_or(Error::InvalidIdna)?;
+        n = m;
+        for &c in &input {
+            if c < n {
+                delta = delta.checked_add(1).ok_or(Error::InvalidIdna)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+    Ok(output)
+}
+
+/// Decode a punycode string, given without the `xn--` prefix
+pub fn punycode_decode(input: &str) -> Result<String, Error> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input)
+    };
+    if !basic.is_ascii() {
+        return Err(Error::InvalidIdna);
+    }
+    let mut output : Vec<char> = basic.chars().collect();
+    let mut n = INITIAL_N;
+    let mut i = 0u32;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.chars().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w = 1u32;
+        let mut k = BASE;
+        loop {
+            let digit = digits.next().and_then(decode_digit).ok_or(Error::InvalidIdna)?;
+            i = digit.checked_mul(w).and_then(|x| i.checked_add(x))
+                .ok_or(Error::InvalidIdna)?;
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t).ok_or(Error::InvalidIdna)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len).ok_or(Error::InvalidIdna)?;
+        i %= len;
+        let c = char::from_u32(n).ok_or(Error::InvalidIdna)?;
+        output.insert(i as usize, c);
+        i += 1;
+    }
+    Ok(output.into_iter().collect())
+}
+
+fn is_dot(c: char) -> bool {
+    c == '.' || c == '\u{3002}' || c == '\u{FF0E}' || c == '\u{FF61}'
+}
+
+/// Split a presentation-format name at its dots, leaving escaped dots in
+/// the labels
+fn split_labels(s: &str) -> Vec<&str> {
+    let mut labels = Vec::new();
+    let mut start = 0;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        }
+        else if is_dot(c) {
+            labels.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    labels.push(&s[start..]);
+    labels
+}
+
+/// RFC 5891 section 4.2.3.1, which UTS #46 only applies with
+/// `CheckHyphens`, and that would reject ASCII labels such as `r3---sn`
+fn check_hyphens(label: &str) -> Result<(), Error> {
+    if let Some(encoded) = label.strip_prefix(ACE_PREFIX) {
+        let unicode : Vec<char> = punycode_decode(encoded)?.chars().collect();
+        if unicode.first() == Some(&'-') || unicode.last() == Some(&'-')
+            || (unicode.get(2) == Some(&'-') && unicode.get(3) == Some(&'-'))
+        {
+            return Err(Error::InvalidIdna);
+        }
+    }
+    Ok(())
+}
+
+/// Convert a name that may contain Unicode labels to its ASCII form.  A
+/// trailing dot makes the name fully qualified, as with `Name::from_str`.
+pub fn to_ascii(s: &str) -> Result<String, Error> {
+    let mut labels = split_labels(s);
+    let fqdn = labels.len() > 1 && labels.last() == Some(&"");
+    if fqdn {
+        labels.pop();
+    }
+    //labels with escapes are left to `Name::from_str`; the rest are mapped
+    //together, as the bidi rule looks at the whole name
+    let plain : Vec<&str> = labels.iter().cloned().filter(|l| !l.contains('\\')).collect();
+    let joined = plain.join(".");
+    let mut mapped = if plain.is_empty() {
+        Vec::new()
+    }
+    else {
+        let ascii = Uts46::new().to_ascii(joined.as_bytes(), AsciiDenyList::EMPTY,
+            Hyphens::Allow, DnsLength::Ignore).map_err(|_| Error::InvalidIdna)?;
+        ascii.split('.').map(String::from).collect()
+    }.into_iter();
+    let mut out = Vec::with_capacity(labels.len() + 1);
+    for label in labels {
+        if label.contains('\\') {
+            let lower = label.to_ascii_lowercase();
+            if !label.is_ascii() || lower.starts_with(ACE_PREFIX) {
+                return Err(Error::InvalidIdna);
+            }
+            out.push(lower);
+        }
+        else {
+            //a mapping can't add dots, so the labels still line up
+            let ascii = mapped.next().ok_or(Error::InvalidIdna)?;
+            check_hyphens(&ascii)?;
+            out.push(ascii);
+        }
+    }
+    if mapped.next().is_some() {
+        return Err(Error::InvalidIdna);
+    }
+    if fqdn {
+        out.push(String::new());
+    }
+    Ok(out.join("."))
+}
+
+/// Convert each `xn--` label of `name` to Unicode.  Labels that aren't
+/// valid A-labels are left as they are.
+pub fn to_unicode(name: &Name) -> String {
+    let labels : Vec<String> = name.iter().map(|l| {
+        let ascii = l.to_string();
+        let lower = ascii.to_ascii_lowercase();
+        if lower.starts_with(ACE_PREFIX) {
+            let (unicode, valid) = Uts46::new().to_unicode(lower.as_bytes(),
+                AsciiDenyList::EMPTY, Hyphens::Allow);
+            if valid.is_ok() && to_ascii(&unicode).ok().as_ref() == Some(&lower) {
+                return unicode.into_owned();
+            }
+        }
+        ascii
+    }).collect();
+    let mut s = labels.join(".");
+    if name.is_fqdn() {
+        s.push('.');
+    }
+    s
+}
+
+impl Name {
+    /// Parse a name that may contain Unicode labels, converting them to
+    /// A-labels
+    pub fn from_unicode(s: &str) -> Result<Name, Error> {
+        Name::from_str(&to_ascii(s)?)
+    }
+    /// The name with any A-labels shown in Unicode
+    pub fn to_unicode(&self) -> String {
+        to_unicode(self)
+    }
+}
@@ -1,42 +1,121 @@
-#[macro_use(quick_error)] extern crate quick_error;
-extern crate byteorder;
-extern crate itertools;
-
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        IOError(err: std::io::Error) {
-            from()
-            description("I/O Error")
-            display("I/O Error: {}", err)
-            cause(err)
-        }
-        InvalidOpt {
-            description("Invalid OPT additional record")
-        }
-        MultipleOpt {
-            description("Multiple OPT additional records found")
-        }
-        UnknownLabelFormat {
-            description("Label in domain name has unknown label format")
-        }
-        ParserStateError {
-            description("Invalid parser state")
-        }
-        NameTooLong {
-            description("Domain name is too long")
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")] #[macro_use(quick_error)] extern crate quick_error;
+#[cfg_attr(not(feature = "std"), macro_use)] extern crate alloc;
+//without std, paths like std::fmt resolve to core instead
+#[cfg(not(feature = "std"))] extern crate core as std;
+#[cfg(feature = "bytes")] extern crate bytes;
+//renamed so it doesn't clash with our own `idna` module
+#[cfg(feature = "idna")] extern crate idna as uts46;
+
+use std::fmt;
+use std::error;
+
+/// Errors from parsing and serializing DNS data
+///
+/// Written out rather than generated with `quick_error!`, which needs `std`.
+#[derive(Debug)]
+pub enum Error {
+    InvalidOpt,
+    MultipleOpt,
+    UnknownLabelFormat(usize),
+    InvalidPointer(usize),
+    ParserStateError,
+    NameTooLong,
+    LabelTooLong,
+    EmptyLabel,
+    InvalidLabel,
+    InvalidMnemonic,
+    InvalidOpcode(u16),
+    InvalidResponseCode(u16),
+    InvalidEdnsOption,
+    StringTooLong,
+    InvalidEscape,
+    SizeLimit,
+    InvalidPrefix,
+    InvalidIdna,
+    RdataOverrun,
+    RdataTooLong,
+    TrailingRdata(usize),
+    TrailingData(usize),
+    UnexpectedEnd,
+    BufferFull,
+    TypeRegistered(u16),
+    RelativeName,
+    Parse(ParseError)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidOpt => write!(f, "Invalid OPT additional record"),
+            Error::MultipleOpt => write!(f, "Multiple OPT additional records found"),
+            Error::UnknownLabelFormat(offset) =>
+                write!(f, "Unknown label format at offset {}", offset),
+            Error::InvalidPointer(offset) =>
+                write!(f, "Invalid compression pointer at offset {}", offset),
+            Error::ParserStateError => write!(f, "Invalid parser state"),
+            Error::NameTooLong => write!(f, "Domain name is longer than 255 octets"),
+            Error::LabelTooLong => write!(f, "DNS label is longer than 63 octets"),
+            Error::EmptyLabel => write!(f, "Empty DNS label"),
+            Error::InvalidLabel => write!(f, "Invalid characters in DNS label"),
+            Error::InvalidMnemonic => write!(f, "Unknown type or class mnemonic"),
+            Error::InvalidOpcode(code) => write!(f, "Invalid opcode {}", code),
+            Error::InvalidResponseCode(code) =>
+                write!(f, "Response code {} doesn't fit in 12 bits", code),
+            Error::InvalidEdnsOption => write!(f, "Invalid EDNS option"),
+            Error::StringTooLong =>
+                write!(f, "Character string is longer than 255 octets"),
+            Error::InvalidEscape =>
+                write!(f, "Invalid escape or quoting in presentation format"),
+            Error::SizeLimit =>
+                write!(f, "Size limit is too small to hold the message header"),
+            Error::InvalidPrefix => write!(f, "Invalid network prefix length"),
+            Error::InvalidIdna => write!(f, "Invalid internationalized domain name"),
+            Error::RdataOverrun => write!(f, "Record data extends past its RDLENGTH"),
+            Error::RdataTooLong => write!(f, "Record data is longer than 65535 octets"),
+            Error::TrailingRdata(count) =>
+                write!(f, "{} unparsed octets at the end of record data", count),
+            Error::TrailingData(count) =>
+                write!(f, "{} octets after the end of the message", count),
+            Error::UnexpectedEnd => write!(f, "Unexpected end of data"),
+            Error::BufferFull => write!(f, "Output buffer is full"),
+            Error::TypeRegistered(code) =>
+                write!(f, "Record type {} already has a representation", code),
+            Error::RelativeName => write!(f, "Relative names can't be serialized"),
+            Error::Parse(ref err) => write!(f, "{}", err)
         }
-        InvalidLabel {
-            description("Invalid characters in DNS label")
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Parse(ref err) => Some(err),
+            _ => None
         }
     }
 }
 
+/// The parts of the standard prelude that live in `alloc`, so that modules
+/// read the same with and without `std`
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+}
+
+mod wire;
+pub use self::wire::{WireReader, WireWriter, SliceWriter};
+
+mod parse_error;
+pub use self::parse_error::{ParseError, Section};
+
 mod enums;
 pub use self::enums::{Class, Type, Opcode, ResponseCode};
 
 mod name;
-pub use self::name::{Label, Name};
+pub use self::name::{Label, Name, Compressor, Ancestors};
 
 mod question;
 pub use self::question::Question;
@@ -44,11 +123,33 @@ pub use self::question::Question;
 mod rr;
 pub use self::rr::{ResourceRecord, OptRecord, RRType};
 pub use self::rr::{RRData, SrvRecord, SoaRecord, MxRecord};
+pub use self::rr::{TxtRecord, CharacterString};
+
+mod registry;
+pub use self::registry::{Registry, RegisteredRecord};
+
+#[cfg(feature = "idna")]
+pub mod idna;
+
+pub mod edns;
+pub use self::edns::EdnsOption;
 
 mod message;
 pub use self::message::Message;
 
+mod view;
+pub use self::view::{MessageRef, NameRef, QuestionRef, RecordRef};
+pub use self::view::{LabelRefs, QuestionRefs, RecordRefs};
+
+mod reader;
+pub use self::reader::RecordReader;
+
 pub mod types;
 
+#[cfg(feature = "std")]
+pub mod zone;
+#[cfg(feature = "std")]
+pub use self::zone::{ZoneParser, ZoneWriter, ZoneError};
+
 #[cfg(test)]
 mod tests;
@@ -1,10 +1,12 @@
 use std::fmt;
-use std::io::{Cursor, Write, Read};
+use std::cmp;
 //use std::slice::SliceIndex;
-use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
 
-use super::{Question, ResourceRecord, OptRecord, Error, Opcode, ResponseCode};
+use super::{Question, ResourceRecord, OptRecord, Compressor, Error, Opcode, ResponseCode};
+use super::prelude::*;
+use super::{WireReader, WireWriter, SliceWriter, Registry};
 use super::rr::ResourceRecordAddl;
+use super::parse_error::{Section, field, locate};
 
 pub use std::slice::{Iter, IterMut};
 
@@ -17,17 +19,17 @@ struct Header {
     pub truncated: bool,
     pub recursion_desired: bool,
     pub recursion_available: bool,
+    /// Reserved, but kept so that messages round-trip exactly
+    pub z: bool,
     pub authenticated_data: bool,
     pub checking_disabled: bool,
     pub response_code: ResponseCode
 }
 
 impl Header {
-    pub fn parse<T>(cursor: &mut Cursor<T>) -> Result<Header, Error> 
-        where Cursor<T> : Read
-    {
-        let id = cursor.read_u16::<BigEndian>()?;
-        let flags = cursor.read_u16::<BigEndian>()?;
+    pub fn parse(r: &mut WireReader) -> Result<Header, Error> {
+        let id = r.read_u16()?;
+        let flags = r.read_u16()?;
         Ok(Header {
             id: id,
             query: flags & (1 << 15) == 0,
@@ -36,31 +38,26 @@ impl Header {
             truncated: flags & (1 << 9) != 0,
             recursion_desired: flags & (1 << 8) != 0,
             recursion_available: flags & (1 << 7) != 0,
-            authenticated_data: false, //FIXME
-            checking_disabled: false, //FIXME
+            z: flags & (1 << 6) != 0,
+            authenticated_data: flags & (1 << 5) != 0,
+            checking_disabled: flags & (1 << 4) != 0,
             response_code: ResponseCode::from(flags & 0b1111)})
     }
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T> : Write
-    {
-        cursor.write_u16::<BigEndian>(self.id)?;
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        w.write_u16(self.id)?;
         let mut flags = 0u16;
         if !self.query { flags |= 1 << 15; }
-        flags |= match self.opcode {
-            Opcode::StandardQuery => 0,
-            Opcode::InverseQuery => 1,
-            Opcode::ServerStatusRequest => 2,
-            _ => 3 //FIXME
-        } << 11;
+        flags |= self.opcode.to_code()? << 11;
         if self.authoritative { flags |= 1 << 10; }
         if self.truncated { flags |= 1 << 9; }
         if self.recursion_desired { flags |= 1 << 8; }
         if self.recursion_available {flags |= 1 << 7; }
-        //FIXME: authenticated_data, checking_disabled
+        if self.z { flags |= 1 << 6; }
+        if self.authenticated_data { flags |= 1 << 5; }
+        if self.checking_disabled { flags |= 1 << 4; }
         let x : u16 = self.response_code.into();
-        flags |= x;
-        cursor.write_u16::<BigEndian>(flags)?;
-        Ok(())
+        flags |= x & 0b1111;
+        w.write_u16(flags)
     }
 }
 
@@ -76,17 +73,19 @@ pub struct Message {
 
 impl Message {
     pub fn parse(data: &[u8]) -> Result<Self, Error> {
-        let mut curs = Cursor::new(&data);
-        Self::parse_new(&mut curs)
-    }
-    pub fn parse_new<T>(cursor: &mut Cursor<T>) -> Result<Self, Error> 
-        where Cursor<T> : Read
-    {
-        let header = Header::parse(cursor)?;
-        let num_questions = cursor.read_u16::<BigEndian>()?;
-        let num_answers = cursor.read_u16::<BigEndian>()?;
-        let num_authority = cursor.read_u16::<BigEndian>()?;
-        let num_additional = cursor.read_u16::<BigEndian>()?;
+        Self::parse_new(&mut WireReader::new(data))
+    }
+    /// Parse a message, decoding the record types in `registry` too
+    pub fn parse_with(registry: &Registry, data: &[u8]) -> Result<Self, Error> {
+        Self::parse_new(&mut WireReader::with_registry(data, registry))
+    }
+    /// Parse a message that takes up the rest of the reader's data
+    pub fn parse_new(r: &mut WireReader) -> Result<Self, Error> {
+        let (header, num_questions, num_answers, num_authority, num_additional) =
+            field(r, "header", |r| {
+                Ok((Header::parse(r)?, r.read_u16()?, r.read_u16()?,
+                    r.read_u16()?, r.read_u16()?))
+            })?;
         let mut msg = Message {
             head: header,
             questions: Vec::with_capacity(num_questions as usize),
@@ -95,17 +94,28 @@ impl Message {
             additional: Vec::with_capacity(num_additional as usize),
             opt: None
         };
-        for _ in 0..num_questions {
-            msg.questions.push(Question::parse(cursor)?);
+        for i in 0..num_questions as usize {
+            let start = r.position();
+            let q = Question::parse(r)
+                .map_err(|e| locate(e, start, Section::Question, i, "question"))?;
+            msg.questions.push(q);
         }
-        for _ in 0..num_answers {
-            msg.answers.push(ResourceRecord::parse(cursor)?);
+        for i in 0..num_answers as usize {
+            let start = r.position();
+            let rr = ResourceRecord::parse(r)
+                .map_err(|e| locate(e, start, Section::Answer, i, "record"))?;
+            msg.answers.push(rr);
         }
-        for _ in 0..num_authority {
-            msg.authority.push(ResourceRecord::parse(cursor)?);
+        for i in 0..num_authority as usize {
+            let start = r.position();
+            let rr = ResourceRecord::parse(r)
+                .map_err(|e| locate(e, start, Section::Authority, i, "record"))?;
+            msg.authority.push(rr);
         }
-        for _ in 0..num_additional {
-            let rr = ResourceRecord::parse_additional(cursor)?;
+        for i in 0..num_additional as usize {
+            let start = r.position();
+            let rr = ResourceRecord::parse_additional(r)
+                .map_err(|e| locate(e, start, Section::Additional, i, "record"))?;
             match rr {
                 ResourceRecordAddl::RR(x) => msg.additional.push(x),
                 ResourceRecordAddl::OPT(x) => {
@@ -113,11 +123,17 @@ impl Message {
                         msg.opt = Some(x);
                     }
                     else {
-                        return Err(Error::MultipleOpt);
+                        return Err(locate(Error::MultipleOpt, start, Section::Additional, i,
+                            "at most one OPT record"));
                     }
                 }
             }
         }
+        if r.remaining() != 0 {
+            let e = Error::TrailingData(r.remaining());
+            return Err(locate(e, r.position(), Section::Additional, num_additional as usize,
+                "end of message"));
+        }
         Ok(msg)
     }
     pub fn from_header(msg: &Self) -> Self {
@@ -131,6 +147,7 @@ impl Message {
                 truncated: h.truncated,
                 recursion_desired: h.recursion_desired,
                 recursion_available: h.recursion_available,
+                z: h.z,
                 authenticated_data: h.authenticated_data,
                 checking_disabled: h.checking_disabled,
                 response_code: h.response_code,
@@ -153,6 +170,7 @@ impl Message {
                 truncated: false,
                 recursion_desired: true,
                 recursion_available: false,
+                z: false,
                 authenticated_data: false,
                 checking_disabled: false,
                 response_code: ResponseCode::NoError,
@@ -164,8 +182,10 @@ impl Message {
             opt: None
         }
     }
-    pub fn new_error(id: u16, rc: ResponseCode) -> Self {
-        Message {
+    /// An empty response carrying `rc`, with an OPT record if the code
+    /// needs one
+    pub fn new_error(id: u16, rc: ResponseCode) -> Result<Self, Error> {
+        let mut msg = Message {
             head: Header {
                 id: id,
                 query:false,
@@ -174,16 +194,19 @@ impl Message {
                 truncated: false,
                 recursion_desired: false,
                 recursion_available: true,
+                z: false,
                 authenticated_data: false,
                 checking_disabled: false,
-                response_code: rc
+                response_code: ResponseCode::NoError
             },
             questions: Vec::new(),
             answers: Vec::new(),
             authority: Vec::new(),
             additional: Vec::new(),
             opt: None
-        }
+        };
+        msg.set_response_code(rc)?;
+        Ok(msg)
     }
     pub fn new_response(id: u16) -> Self {
         Message {
@@ -195,6 +218,7 @@ impl Message {
                 truncated: false,
                 recursion_desired: true,
                 recursion_available: true,
+                z: false,
                 authenticated_data: false,
                 checking_disabled: false,
                 response_code: ResponseCode::NoError,
@@ -245,17 +269,45 @@ impl Message {
     pub fn set_recursion_available(&mut self, ra: bool) {
         self.head.recursion_available = ra;
     }
+    pub fn authenticated_data(&self) -> bool {
+        self.head.authenticated_data
+    }
+    pub fn set_authenticated_data(&mut self, ad: bool) {
+        self.head.authenticated_data = ad;
+    }
+    pub fn checking_disabled(&self) -> bool {
+        self.head.checking_disabled
+    }
+    pub fn set_checking_disabled(&mut self, cd: bool) {
+        self.head.checking_disabled = cd;
+    }
     pub fn opcode(&self) -> Opcode {
         self.head.opcode
     }
     pub fn set_opcode(&mut self, o: Opcode) {
         self.head.opcode = o;
     }
+    /// The full response code: the header's 4 bits combined with the upper
+    /// 8 bits from the OPT record, if there is one
     pub fn response_code(&self) -> ResponseCode {
-        self.head.response_code
-    }
-    pub fn set_response_code(&mut self, r: ResponseCode) {
-        self.head.response_code = r;
+        let low : u16 = self.head.response_code.into();
+        let high = self.opt.as_ref().map_or(0, |o| o.extrcode as u16);
+        ResponseCode::from(high << 4 | (low & 0b1111))
+    }
+    /// Set the response code, splitting it between the header and the OPT
+    /// record.  Codes above 15 need an OPT record, so one is added if the
+    /// message doesn't have one yet.  Codes that don't fit in 12 bits are
+    /// rejected and leave the message unchanged.
+    pub fn set_response_code(&mut self, r: ResponseCode) -> Result<(), Error> {
+        let code = r.to_code()?;
+        self.head.response_code = ResponseCode::from(code & 0b1111);
+        if code > 0b1111 && self.opt.is_none() {
+            self.opt = Some(OptRecord::new(512));
+        }
+        if let Some(ref mut opt) = self.opt {
+            opt.extrcode = (code >> 4) as u8;
+        }
+        Ok(())
     }
     pub fn num_questions(&self) -> usize {
         self.questions.len()
@@ -374,18 +426,132 @@ impl Message {
     }
 
     pub fn serialize(&self) -> Result<Vec<u8>, Error> {
-        let mut curs = Cursor::new(Vec::<u8>::new()); //FIXME: estimate size?
-        try!(self.head.serialize(&mut curs));
-        try!(curs.write_u16::<BigEndian>(self.questions.len() as u16));
-        try!(curs.write_u16::<BigEndian>(self.answers.len() as u16));
-        try!(curs.write_u16::<BigEndian>(self.authority.len() as u16));
-        try!(curs.write_u16::<BigEndian>(self.additional.len() as u16));
-        for q in self.questions.iter() { try!(q.serialize(&mut curs)); }
-        for a in self.answers.iter() { try!(a.serialize(&mut curs)); }
-        for a in self.authority.iter() { try!(a.serialize(&mut curs)); }
-        for a in self.additional.iter() { try!(a.serialize(&mut curs)); }
-        if let Some(ref o) = self.opt { try!(o.serialize(&mut curs)); }
-        return Ok(curs.into_inner());
+        let mut buf = Vec::new(); //FIXME: estimate size?
+        self.serialize_into(&mut buf)?;
+        Ok(buf)
+    }
+    /// Append the message to `w`.  Compression pointers are relative to
+    /// where the message starts, so `w` may already hold a prefix such as
+    /// a TCP length field.
+    pub fn serialize_into<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        self.head.serialize(w)?;
+        w.write_u16(self.questions.len() as u16)?;
+        w.write_u16(self.answers.len() as u16)?;
+        w.write_u16(self.authority.len() as u16)?;
+        let num_opt = if self.opt.is_some() { 1 } else { 0 };
+        w.write_u16((self.additional.len() + num_opt) as u16)?;
+        let mut comp = Compressor::with_base(w.position() - 12);
+        for q in self.questions.iter() { q.serialize_compressed(w, &mut comp)?; }
+        for a in self.answers.iter() { a.serialize_compressed(w, &mut comp)?; }
+        for a in self.authority.iter() { a.serialize_compressed(w, &mut comp)?; }
+        for a in self.additional.iter() { a.serialize_compressed(w, &mut comp)?; }
+        if let Some(ref o) = self.opt { o.serialize(w)?; }
+        Ok(())
+    }
+    /// Serialize the message into at most `max` octets, or into the UDP
+    /// payload size advertised by the OPT record if that is smaller.
+    ///
+    /// Sections are filled in order and records are only ever dropped a
+    /// whole RRset at a time.  The OPT record is always kept.  The TC bit is
+    /// set if any question, answer or authority data had to be dropped;
+    /// running out of room in the additional section does not set it (RFC
+    /// 2181 section 9).
+    pub fn serialize_with_limit(&self, max: usize) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        self.serialize_with_limit_into(max, &mut buf)?;
+        Ok(buf)
+    }
+    /// Like `serialize_with_limit`, but appends to `w`.  A writer with a
+    /// fixed capacity lowers the limit to the room it has left.
+    pub fn serialize_with_limit_into<W: WireWriter + ?Sized>(&self, max: usize, w: &mut W)
+        -> Result<(), Error>
+    {
+        let base = w.position();
+        let mut limit = max;
+        if let Some(cap) = w.capacity() {
+            limit = cmp::min(limit, cap.saturating_sub(base));
+        }
+        let mut opt_data = Vec::new();
+        if let Some(ref o) = self.opt {
+            //RFC 6891 section 6.2.5: values below 512 are treated as 512
+            limit = cmp::min(limit, cmp::max(o.udp as usize, 512));
+            o.serialize(&mut opt_data)?;
+        }
+        if limit < 12 + opt_data.len() {
+            return Err(Error::SizeLimit);
+        }
+        let limit = base + limit - opt_data.len();
+
+        w.write_bytes(&[0u8; 12])?; //header is filled in at the end
+        let mut comp = Compressor::with_base(base);
+        let mut counts = [0u16; 4];
+        let mut dropped = None;
+        for q in self.questions.iter() {
+            let pos = w.position();
+            let res = q.serialize_compressed(w, &mut comp);
+            if Self::overflowed(res, w, limit)? {
+                Self::rollback(w, &mut comp, pos);
+                dropped = Some(0);
+                break;
+            }
+            counts[0] += 1;
+        }
+        let sections = [&self.answers, &self.authority, &self.additional];
+        for (i, section) in sections.iter().enumerate() {
+            if dropped.is_some() { break; }
+            let mut start = 0;
+            while start < section.len() {
+                let mut end = start + 1;
+                while end < section.len() && Self::same_rrset(&section[start], &section[end]) {
+                    end += 1;
+                }
+                let pos = w.position();
+                let res = section[start..end].iter()
+                    .try_for_each(|rr| rr.serialize_compressed(w, &mut comp));
+                if Self::overflowed(res, w, limit)? {
+                    Self::rollback(w, &mut comp, pos);
+                    dropped = Some(i + 1);
+                    break;
+                }
+                counts[i + 1] += (end - start) as u16;
+                start = end;
+            }
+        }
+        if self.opt.is_some() {
+            counts[3] += 1;
+        }
+        w.write_bytes(&opt_data[..])?;
+
+        let mut head = self.head.clone();
+        if let Some(section) = dropped {
+            if section < 3 { head.truncated = true; }
+        }
+        let mut buf = [0u8; 12];
+        {
+            let mut hw = SliceWriter::new(&mut buf);
+            head.serialize(&mut hw)?;
+            for count in counts.iter() {
+                hw.write_u16(*count)?;
+            }
+        }
+        w.patch(base, &buf)
+    }
+    /// Whether a write went past `limit`; running out of buffer counts
+    fn overflowed<W: WireWriter + ?Sized>(res: Result<(), Error>, w: &W, limit: usize)
+        -> Result<bool, Error>
+    {
+        match res {
+            Ok(()) => Ok(w.position() > limit),
+            Err(Error::BufferFull) => Ok(true),
+            Err(e) => Err(e)
+        }
+    }
+    fn rollback<W: WireWriter + ?Sized>(w: &mut W, comp: &mut Compressor, pos: usize) {
+        w.truncate(pos);
+        comp.truncate(pos);
+    }
+    fn same_rrset(a: &ResourceRecord, b: &ResourceRecord) -> bool {
+        a.get_type() == b.get_type() && a.rrclass == b.rrclass && a.rrname == b.rrname
     }
     fn fmt_header(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "DNS Message {:x}:", self.head.id)?;
@@ -404,8 +570,8 @@ impl Message {
         if self.head.authenticated_data { write!(f, " auth")?; }
         if self.head.checking_disabled { write!(f, " nocheck")?; }
         write!(f, "\n")?;
-        if self.head.response_code != ResponseCode::NoError {
-            writeln!(f, "\t{:?}", self.head.response_code)?;
+        if self.response_code() != ResponseCode::NoError {
+            writeln!(f, "\t{:?}", self.response_code())?;
         }
         Ok(())
     }
@@ -439,7 +605,12 @@ impl fmt::Display for Message {
             writeln!(f, "\tRFC 6891 OPT Data:")?;
             writeln!(f, "\t\tEDNS v{}; UDP Max Size {}", opt.version , opt.udp)?;
             writeln!(f, "\t\tFlags {}", opt.flags)?;
-            //TODO: OPT DATA
+            for o in opt.options() {
+                match o {
+                    Ok(o) => writeln!(f, "\t\t{}", o)?,
+                    Err(_) => writeln!(f, "\t\t<malformed option data>")?
+                }
+            }
         }
         Ok(())
     }
@@ -1,58 +1,165 @@
 use std::fmt;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
-use std::io::{Cursor, Write, Read};
-use std::collections::VecDeque;
-use std::collections::vec_deque::Iter as VecDequeIter;
-use byteorder::{WriteBytesExt, ReadBytesExt};
-use itertools::Itertools;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::collections::vec_deque::Iter as VecDequeIter;
 
-use super::Error;
+use super::{Error, WireReader, WireWriter};
+use super::prelude::*;
 
+/// Longest label allowed on the wire (RFC 1035 section 2.3.4)
+pub const MAX_LABEL_LEN: usize = 63;
+/// Compression pointers may not point into the message header
+const HEADER_LEN: usize = 12;
+/// Longest name allowed on the wire, counting length octets and the root
+pub const MAX_NAME_LEN: usize = 255;
+
+/// A single label of a domain name.  Labels hold raw octets: anything is
+/// legal on the wire (RFC 2181 section 11), and the presentation form
+/// escapes whatever isn't printable.
 #[derive(Clone)]
 pub struct Label {
-    data: String
+    data: Vec<u8>
 }
 
 impl Label {
-    fn check(s: &str) -> Result<(), Error> {
-        if !s.chars().all(|c| c.is_digit(36) || c == '-' || c == '_') || s.len() == 0 {
-            return Err(Error::InvalidLabel);
+    fn check(data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Err(Error::EmptyLabel);
+        }
+        if data.len() > MAX_LABEL_LEN {
+            return Err(Error::LabelTooLong);
         }
         Ok(())
     }
+    /// Parse the presentation form, decoding `\X` and `\DDD` escapes
     pub fn from_str(s: &str) -> Result<Self, Error> {
-        Self::from_string(s.to_string())
+        let bytes = s.as_bytes();
+        let mut data = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if i + 1 < bytes.len() && bytes[i + 1].is_ascii_digit() => {
+                    let digits = s.get(i + 1..i + 4).unwrap_or("");
+                    match digits.parse::<u8>() {
+                        Ok(x) if digits.len() == 3 => data.push(x),
+                        _ => return Err(Error::InvalidEscape)
+                    }
+                    i += 4;
+                }
+                b'\\' if i + 1 < bytes.len() && bytes[i + 1].is_ascii() => {
+                    data.push(bytes[i + 1]);
+                    i += 2;
+                }
+                b'\\' => return Err(Error::InvalidEscape),
+                //presentation form is ASCII, anything else must be escaped
+                b'.' | 0x80..=0xff => return Err(Error::InvalidLabel),
+                x => {
+                    data.push(x);
+                    i += 1;
+                }
+            }
+        }
+        Self::from_vec(data)
     }
     pub fn from_string(s: String) -> Result<Self, Error> {
-        try!(Self::check(s.as_str()));
-        Ok(Label { data: s })
+        Self::from_str(s.as_str())
     }
-    pub fn as_str(&self) -> &str {
-        self.data.as_str()
+    /// Build a label from raw octets, without any unescaping
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        Self::from_vec(data.to_vec())
     }
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error> 
-        where Cursor<T> : Write
-    {
-        cursor.write_u8(self.data.len() as u8)?;
-        if let Err(e) = cursor.write_all(self.as_str().as_bytes()) {
-            return Err(Error::IOError(e));
-        }
-        Ok(())
+    pub fn from_vec(data: Vec<u8>) -> Result<Self, Error> {
+        Self::check(&data[..])?;
+        Ok(Label { data })
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..]
+    }
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        w.write_u8(self.data.len() as u8)?;
+        w.write_bytes(self.as_bytes())
     }
+    /// Number of octets in the label, excluding the length octet
     pub fn len(&self) -> usize {
         self.data.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+    /// Whether this is the `*` label of a wildcard name (RFC 4592)
+    pub fn is_wildcard(&self) -> bool {
+        &self.data[..] == b"*"
+    }
+    /// A copy of the label with ASCII letters lowercased
+    pub fn to_lowercase(&self) -> Label {
+        Label { data: self.data.to_ascii_lowercase() }
+    }
+    /// Whether the label follows the letter-digit-hyphen rule for host
+    /// names (RFC 952, RFC 1123 section 2.1)
+    pub fn is_hostname(&self) -> bool {
+        self.data.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'-') &&
+            self.data[0] != b'-' && self.data[self.data.len() - 1] != b'-'
+    }
+}
+
+// Labels compare without regard to ASCII case (RFC 4343), and order as
+// lowercased octet strings (RFC 4034 section 6.1)
+impl PartialEq for Label {
+    fn eq(&self, other: &Label) -> bool {
+        self.data.eq_ignore_ascii_case(&other.data)
+    }
+}
+
+impl Eq for Label {}
+
+impl Hash for Label {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.data.len());
+        for c in &self.data {
+            state.write_u8(c.to_ascii_lowercase());
+        }
+    }
+}
+
+impl Ord for Label {
+    fn cmp(&self, other: &Label) -> Ordering {
+        let a = self.data.iter().map(|c| c.to_ascii_lowercase());
+        let b = other.data.iter().map(|c| c.to_ascii_lowercase());
+        a.cmp(b)
+    }
+}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Label) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl fmt::Display for Label {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.data.fmt(f)
+        for &c in &self.data {
+            match c {
+                b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' | b'$' | b' ' =>
+                    write!(f, "\\{}", c as char)?,
+                0x21..=0x7e => write!(f, "{}", c as char)?,
+                _ => write!(f, "\\{:03}", c)?
+            }
+        }
+        Ok(())
     }
 }
 
+/// A domain name.  Names are either fully qualified (absolute, ending in
+/// the root) or relative to some origin that is not yet known.  Only fully
+/// qualified names can be written to the wire; `qualify` a relative name
+/// first.
 #[derive(Clone)]
 pub struct Name {
-    labels: VecDeque<Label>
+    labels: VecDeque<Label>,
+    fqdn: bool
 }
 
 enum ParseLabel {
@@ -62,14 +169,154 @@ enum ParseLabel {
 }
 
 impl Name {
+    /// The root name, which has no labels
+    pub fn root() -> Self {
+        Name { labels: VecDeque::new(), fqdn: true }
+    }
     pub fn from_string(s: String) -> Result<Self, Error> {
         Self::from_str(s.as_str())
     }
+    /// The labels joined with dots, with a trailing dot if the name is
+    /// fully qualified, so that `from_str` gives the same name back.  The
+    /// root is written as `.`.
     pub fn to_string(&self) -> String {
-        self.iter().join(".")
+        let labels : Vec<String> = self.iter().map(|l| l.to_string()).collect();
+        let mut s = labels.join(".");
+        if self.fqdn {
+            s.push('.');
+        }
+        s
+    }
+    /// Whether the name is absolute rather than relative to an origin
+    pub fn is_fqdn(&self) -> bool {
+        self.fqdn
+    }
+    pub fn is_root(&self) -> bool {
+        self.fqdn && self.labels.is_empty()
+    }
+    /// The part of the name below `origin`, as a relative name.  Gives
+    /// `None` if the name is not within `origin`, and an empty relative
+    /// name if it is `origin` itself.
+    pub fn relativize(&self, origin: &Name) -> Option<Name> {
+        if !self.is_subdomain_of(origin) {
+            return None;
+        }
+        let n = self.num_labels() - origin.num_labels();
+        Some(Name { labels: self.labels.iter().take(n).cloned().collect(), fqdn: false })
+    }
+    /// Remove `zone` from the end of the name, if the name is within it.
+    /// The same as `relativize`.
+    pub fn strip_suffix(&self, zone: &Name) -> Option<Name> {
+        self.relativize(zone)
+    }
+    /// Number of labels, not counting the root
+    pub fn num_labels(&self) -> usize {
+        self.labels.len()
+    }
+    /// The name with its leftmost label removed, or `None` for the root
+    pub fn parent(&self) -> Option<Name> {
+        if self.labels.is_empty() {
+            return None;
+        }
+        let mut parent = self.clone();
+        parent.labels.pop_front();
+        Some(parent)
+    }
+    /// Whether the name is `other` or below it.  Every absolute name is
+    /// within the root.
+    pub fn is_subdomain_of(&self, other: &Name) -> bool {
+        let n = self.labels.len();
+        let m = other.labels.len();
+        n >= m && self.fqdn == other.fqdn && self.labels.iter().skip(n - m).eq(other.iter())
     }
-    pub fn push(&mut self, l: Label) {
+    /// The longest name that both names are within
+    pub fn common_ancestor(&self, other: &Name) -> Name {
+        let common = self.labels.iter().rev().zip(other.labels.iter().rev())
+            .take_while(|&(a, b)| a == b)
+            .count();
+        let n = self.labels.len();
+        Name {
+            labels: self.labels.iter().skip(n - common).cloned().collect(),
+            fqdn: self.fqdn && other.fqdn
+        }
+    }
+    /// A new name with `l` added on the left, checking that it still fits
+    /// in 255 octets on the wire
+    pub fn prepend_label(&self, l: Label) -> Result<Name, Error> {
+        let mut name = self.clone();
+        name.push(l)?;
+        Ok(name)
+    }
+    /// The name and each of its ancestors in turn, ending with the root
+    /// (or with the last label of a relative name)
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors { next: Some(self.clone()) }
+    }
+    /// Octets taken up on the wire without compression, including the
+    /// length octets and the terminating root label
+    pub fn wire_len(&self) -> usize {
+        self.labels.iter().map(|l| l.len() + 1).sum::<usize>() + 1
+    }
+    fn checked(self) -> Result<Name, Error> {
+        if self.wire_len() > MAX_NAME_LEN {
+            return Err(Error::NameTooLong);
+        }
+        Ok(self)
+    }
+    /// The canonical form of the name, with ASCII letters lowercased
+    pub fn to_lowercase(&self) -> Name {
+        Name { labels: self.labels.iter().map(|l| l.to_lowercase()).collect(), fqdn: self.fqdn }
+    }
+    /// Append `origin` to a relative name.  Names that are already fully
+    /// qualified are returned unchanged.
+    pub fn qualify(&self, origin: &Name) -> Result<Name, Error> {
+        if self.fqdn {
+            return Ok(self.clone());
+        }
+        let mut labels = self.labels.clone();
+        labels.extend(origin.labels.iter().cloned());
+        Name { labels, fqdn: origin.fqdn }.checked()
+    }
+    /// The name in master file form: absolute with a trailing dot, or, if
+    /// it is within `origin`, relative to it (`@` for the origin itself).
+    /// Relative names are written as they are.
+    pub fn to_presentation(&self, origin: Option<&Name>) -> String {
+        let relative = match origin {
+            Some(o) => self.relativize(o),
+            None if !self.fqdn => Some(self.clone()),
+            None => None
+        };
+        match relative {
+            Some(ref r) if r.labels.is_empty() => "@".to_string(),
+            Some(r) => r.to_string(),
+            None => self.to_string()
+        }
+    }
+    /// Whether the leftmost label is `*` (RFC 4592)
+    pub fn is_wildcard(&self) -> bool {
+        self.labels.front().is_some_and(|l| l.is_wildcard())
+    }
+    /// Whether every label follows the host name rules.  This is stricter
+    /// than the DNS itself, which allows any octets in a label.
+    pub fn is_hostname(&self) -> bool {
+        self.labels.iter().all(|l| l.is_hostname())
+    }
+    /// Parse a name, additionally requiring it to be a valid host name
+    pub fn from_hostname(s: &str) -> Result<Self, Error> {
+        let name = Self::from_str(s)?;
+        if !name.is_hostname() {
+            return Err(Error::InvalidLabel);
+        }
+        Ok(name)
+    }
+    /// Add `l` on the left, failing if the name would no longer fit on
+    /// the wire
+    pub fn push(&mut self, l: Label) -> Result<(), Error> {
+        if self.wire_len() + 1 + l.len() > MAX_NAME_LEN {
+            return Err(Error::NameTooLong);
+        }
         self.labels.push_front(l);
+        Ok(())
     }
     pub fn pop(&mut self) {
         self.labels.pop_front();
@@ -77,73 +324,332 @@ impl Name {
     pub fn iter(&self) -> VecDequeIter<Label> {
         self.labels.iter()
     }
+    /// Number of octets in the labels themselves; see also `wire_len`
     pub fn len(&self) -> usize {
         self.labels.iter().map(|l| l.len()).fold(0, |acc, x| acc + x)
     }
-    fn read_label<T>(cursor: &mut Cursor<T>, buf: &mut [u8]) -> Result<ParseLabel, Error>
-        where Cursor<T> : Read
-    {
-        let len = cursor.read_u8()?;
+    fn read_label(r: &mut WireReader) -> Result<ParseLabel, Error> {
+        let len = r.read_u8()?;
         if len == 0 { return Ok(ParseLabel::End); }
         if len >> 6 == 0b11 {
             let mut dest = (len & 0b0011_1111) as u16;
-            dest = (dest << 8) | (cursor.read_u8()? as u16);
+            dest = (dest << 8) | (r.read_u8()? as u16);
             return Ok(ParseLabel::Pointer(dest));
         }
         if len >> 6 == 0b00 {
-            cursor.read_exact(&mut buf[0..(len as usize)])?;
-            let label = Label::from_str(&*String::from_utf8_lossy(&buf[0..(len as usize)]))?;
+            let label = Label::from_bytes(r.read_bytes(len as usize)?)?;
             return Ok(ParseLabel::L(label));
         }
-        return Err(Error::UnknownLabelFormat);
+        //0b01 and 0b10 are reserved (RFC 6891 section 5)
+        Err(Error::UnknownLabelFormat(r.position() - 1))
     }
-    pub fn parse<T>(cursor: &mut Cursor<T>) -> Result<Name, Error>
-        where Cursor<T> : Read
-    {
-        let mut name = Name { labels: VecDeque::new() };
-        let mut pos = 0u64; //always invalid
-        let mut buf = [0u8; 64];
-        for _ in 1..128 { //prevent malicious packets from causing infinite loop
-            match Self::read_label(cursor, &mut buf)? {
+    /// Parse a possibly compressed name.  Each compression pointer must
+    /// point to the start of a label the reader has already seen, strictly
+    /// before every label of this name and past the header, so pointer
+    /// loops are impossible and a name is read in at most one pass over the
+    /// message.
+    pub fn parse(r: &mut WireReader) -> Result<Name, Error> {
+        let mut name = Name::root();
+        let mut ret = None; //where to continue after the first pointer
+        let mut lowest = r.position();
+        loop {
+            let pos = r.position();
+            match Self::read_label(r)? {
                 ParseLabel::End => {
-                    if name.len() > 255 {
-                        return Err(Error::NameTooLong);
-                    }
-                    if pos != 0 {
-                        cursor.set_position(pos);
+                    if let Some(pos) = ret {
+                        r.set_position(pos);
                     }
                     return Ok(name);
                 }
                 ParseLabel::L(l) => {
+                    if name.wire_len() + 1 + l.len() > MAX_NAME_LEN {
+                        return Err(Error::NameTooLong);
+                    }
                     name.labels.push_back(l);
+                    r.add_label(pos);
                 }
                 ParseLabel::Pointer(off) => {
-                    if pos == 0 {
-                        pos = cursor.position();
+                    let off = off as usize;
+                    if off >= lowest || off < HEADER_LEN || !r.is_label(off) {
+                        return Err(Error::InvalidPointer(r.position() - 2));
                     }
-                    cursor.set_position(off as u64);
+                    if ret.is_none() {
+                        ret = Some(r.position());
+                    }
+                    lowest = off;
+                    r.set_position(off);
                 }
             }
         }
-        //maximum label depth exceeded
-        return Err(Error::NameTooLong);
     }
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error> 
-        where Cursor<T> : Write
-    {
+    /// Serialize the name uncompressed.  Only fully qualified names can be
+    /// serialized; `qualify` a relative name first.
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        if !self.fqdn {
+            return Err(Error::RelativeName);
+        }
         for l in self.iter() {
-            try!(l.serialize(cursor));
+            l.serialize(w)?;
         }
-        try!(cursor.write_u8(0));
-        Ok(())
+        w.write_u8(0)
+    }
+    /// Serialize the name, replacing the longest suffix that has already
+    /// been written with a compression pointer (RFC 1035 section 4.1.4)
+    pub fn serialize_compressed<W: WireWriter + ?Sized>(&self, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
+    {
+        self.serialize_tracked(w, comp, true)
+    }
+    /// Serialize the name in full, but remember its suffixes so that later
+    /// names may point to them.  Used where the RFCs forbid compression.
+    pub fn serialize_recorded<W: WireWriter + ?Sized>(&self, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
+    {
+        self.serialize_tracked(w, comp, false)
+    }
+    fn serialize_tracked<W: WireWriter + ?Sized>(&self, w: &mut W,
+        comp: &mut Compressor, compress: bool) -> Result<(), Error>
+    {
+        if !self.fqdn {
+            return Err(Error::RelativeName);
+        }
+        let labels : Vec<&Label> = self.iter().collect();
+        for i in 0..labels.len() {
+            let key = Compressor::key(&labels[i..]);
+            if compress {
+                if let Some(off) = comp.find(&key) {
+                    return w.write_u16(0xC000 | off);
+                }
+            }
+            comp.insert(key, w.position());
+            labels[i].serialize(w)?;
+        }
+        w.write_u8(0)
+    }
+}
+
+// Reverse mapping names (RFC 1035 section 3.5, RFC 3596 section 2.5)
+impl Name {
+    /// The name made of `labels` followed by `suffix`.  The labels are
+    /// short runs of digits, so the name is always valid.
+    fn arpa(labels: Vec<String>, suffix: &[&str]) -> Name {
+        let labels = labels.into_iter().map(String::into_bytes)
+            .chain(suffix.iter().map(|l| l.as_bytes().to_vec()))
+            .map(|data| Label { data })
+            .collect();
+        Name { labels, fqdn: true }
+    }
+    /// The name used to look up the PTR record for `addr`, such as
+    /// `1.2.0.192.in-addr.arpa.` or the nibble form under `ip6.arpa.`
+    pub fn from_ip(addr: IpAddr) -> Name {
+        match addr {
+            IpAddr::V4(a) => {
+                let labels : Vec<String> = a.octets().iter().rev()
+                    .map(|o| o.to_string()).collect();
+                Self::arpa(labels, &["in-addr", "arpa"])
+            }
+            IpAddr::V6(a) => {
+                let labels : Vec<String> = a.octets().iter().rev()
+                    .flat_map(|o| vec![o & 0xF, o >> 4])
+                    .map(|n| format!("{:x}", n)).collect();
+                Self::arpa(labels, &["ip6", "arpa"])
+            }
+        }
+    }
+    /// The address a full reverse mapping name stands for, or `None` if
+    /// the name is not one
+    pub fn to_ip(&self) -> Option<IpAddr> {
+        let labels : Vec<&[u8]> = self.labels.iter().map(|l| l.as_bytes()).collect();
+        let n = labels.len();
+        if !self.fqdn || n < 2 || !labels[n - 1].eq_ignore_ascii_case(b"arpa") {
+            return None;
+        }
+        if n == 6 && labels[4].eq_ignore_ascii_case(b"in-addr") {
+            let mut octets = [0u8; 4];
+            for i in 0..4 {
+                //decimal, without leading zeros
+                let l = labels[i];
+                if l.is_empty() || l.len() > 3 || (l.len() > 1 && l[0] == b'0')
+                    || !l.iter().all(|c| c.is_ascii_digit())
+                {
+                    return None;
+                }
+                let x = l.iter().fold(0u16, |x, &c| x * 10 + (c - b'0') as u16);
+                if x > 255 {
+                    return None;
+                }
+                octets[3 - i] = x as u8;
+            }
+            return Some(IpAddr::V4(Ipv4Addr::from(octets)));
+        }
+        if n == 34 && labels[32].eq_ignore_ascii_case(b"ip6") {
+            let mut octets = [0u8; 16];
+            for i in 0..32 {
+                let nibble = match labels[i] {
+                    &[c] => (c as char).to_digit(16)? as u8,
+                    _ => return None
+                };
+                octets[15 - i / 2] |= nibble << (4 * (i % 2));
+            }
+            return Some(IpAddr::V6(Ipv6Addr::from(octets)));
+        }
+        None
+    }
+    /// The zone for a classless `in-addr.arpa` delegation of the network
+    /// containing `addr` (RFC 2317), such as `64/26.2.0.192.in-addr.arpa.`.
+    /// `prefix` must be between 25 and 32.
+    pub fn classless_zone(addr: Ipv4Addr, prefix: u8) -> Result<Name, Error> {
+        if prefix <= 24 || prefix > 32 {
+            return Err(Error::InvalidPrefix);
+        }
+        let o = addr.octets();
+        let net = o[3] & (0xFFu8 << (32 - prefix));
+        let labels = vec![format!("{}/{}", net, prefix), o[2].to_string(),
+            o[1].to_string(), o[0].to_string()];
+        Ok(Self::arpa(labels, &["in-addr", "arpa"]))
+    }
+    /// The name the PTR record for `addr` has within its classless
+    /// delegation, such as `65.64/26.2.0.192.in-addr.arpa.`
+    pub fn from_ip_classless(addr: Ipv4Addr, prefix: u8) -> Result<Name, Error> {
+        let zone = Self::classless_zone(addr, prefix)?;
+        zone.prepend_label(Label::from_string(addr.octets()[3].to_string())?)
+    }
+}
+
+/// Iterator over a name and its ancestors, see `Name::ancestors`
+pub struct Ancestors {
+    next: Option<Name>
+}
+
+impl Iterator for Ancestors {
+    type Item = Name;
+    fn next(&mut self) -> Option<Name> {
+        let name = self.next.take()?;
+        if !name.fqdn && name.labels.len() == 1 {
+            self.next = None;
+        }
+        else {
+            self.next = name.parent();
+        }
+        Some(name)
+    }
+}
+
+/// Remembers where each name suffix was written in a message so that later
+/// occurrences can be compressed.  Offsets are relative to the start of the
+/// message, which is at writer position 0 unless created with `with_base`.
+pub struct Compressor {
+    offsets: BTreeMap<Vec<u8>, u16>,
+    base: usize,
+    enabled: bool
+}
+
+impl Compressor {
+    pub fn new() -> Self {
+        Self::with_base(0)
+    }
+    /// A compressor for a message that starts at writer position `base`,
+    /// e.g. after a TCP length prefix
+    pub fn with_base(base: usize) -> Self {
+        Compressor { offsets: BTreeMap::new(), base, enabled: true }
+    }
+    /// A compressor that never records or emits pointers
+    pub fn disabled() -> Self {
+        Compressor { offsets: BTreeMap::new(), base: 0, enabled: false }
+    }
+    /// Forget every suffix written at or beyond writer position `pos`
+    pub fn truncate(&mut self, pos: usize) {
+        let end = pos.saturating_sub(self.base);
+        self.offsets.retain(|_, off| (*off as usize) < end);
+    }
+    fn key(labels: &[&Label]) -> Vec<u8> {
+        //names compare case-insensitively, so the key is the lowercased
+        //wire form of the suffix
+        let mut key = Vec::new();
+        for l in labels {
+            key.push(l.len() as u8);
+            key.extend(l.as_bytes().iter().map(|b| b.to_ascii_lowercase()));
+        }
+        key
+    }
+    fn find(&self, key: &[u8]) -> Option<u16> {
+        if !self.enabled { return None; }
+        self.offsets.get(key).cloned()
+    }
+    fn insert(&mut self, key: Vec<u8>, pos: usize) {
+        //pointers only have 14 bits of offset
+        let pos = pos - self.base;
+        if self.enabled && pos < 0x4000 {
+            self.offsets.entry(key).or_insert(pos as u16);
+        }
+    }
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl FromStr for Name {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Error> {
-        Ok(Name { labels: s.split('.').map(|s| Label::from_str(s))
-            .collect::<Result<VecDeque<_>, Error>>()? })
+        if s == "." {
+            return Ok(Name::root());
+        }
+        //split on dots, except escaped ones; a final unescaped dot makes the
+        //name fully qualified
+        let mut labels = VecDeque::new();
+        let mut start = 0;
+        let mut escaped = false;
+        for (i, c) in s.char_indices() {
+            if escaped {
+                escaped = false;
+            }
+            else if c == '\\' {
+                escaped = true;
+            }
+            else if c == '.' {
+                labels.push_back(Label::from_str(&s[start..i])?);
+                start = i + 1;
+            }
+        }
+        let fqdn = start == s.len() && start > 0;
+        if !fqdn {
+            labels.push_back(Label::from_str(&s[start..])?);
+        }
+        Name { labels, fqdn }.checked()
+    }
+}
+
+// Names compare label by label, ignoring ASCII case but not whether they
+// are fully qualified.  The ordering is the canonical DNSSEC ordering
+// (RFC 4034 section 6.1), which sorts by the rightmost label first.
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        self.fqdn == other.fqdn && self.labels == other.labels
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fqdn.hash(state);
+        self.labels.hash(state);
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Name) -> Ordering {
+        self.labels.iter().rev().cmp(other.labels.iter().rev())
+            .then(self.fqdn.cmp(&other.fqdn))
+    }
+}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Name) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
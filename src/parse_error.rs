@@ -0,0 +1,94 @@
+use std::fmt;
+use std::error;
+use super::{Error, WireReader};
+use super::prelude::*;
+
+/// The part of a message being parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    Question,
+    Answer,
+    Authority,
+    Additional
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Section::Header => "header",
+            Section::Question => "question",
+            Section::Answer => "answer",
+            Section::Authority => "authority",
+            Section::Additional => "additional"
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Where and why parsing a message failed
+#[derive(Debug)]
+pub struct ParseError {
+    /// Offset from the start of the message of the field that failed
+    pub offset: usize,
+    pub section: Section,
+    /// Index of the question or record within its section
+    pub index: usize,
+    /// What was being read, such as `"record type"`
+    pub expected: &'static str,
+    pub error: Box<Error>
+}
+
+impl ParseError {
+    pub fn new(offset: usize, expected: &'static str, error: Error) -> Self {
+        ParseError {
+            offset,
+            section: Section::Header,
+            index: 0,
+            expected,
+            error: Box::new(error)
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {} in {}", self.expected, self.offset, self.section)?;
+        if self.section != Section::Header {
+            write!(f, " {}", self.index)?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+/// Run `f`, reporting any error as a failure to read `expected` at the
+/// reader's current offset
+pub fn field<'a, R, F>(r: &mut WireReader<'a>, expected: &'static str, f: F) -> Result<R, Error>
+    where F: FnOnce(&mut WireReader<'a>) -> Result<R, Error>
+{
+    let offset = r.position();
+    f(r).map_err(|e| match e {
+        Error::Parse(p) => Error::Parse(p),
+        e => Error::Parse(ParseError::new(offset, expected, e))
+    })
+}
+
+/// Attach the section and index to an error from parsing an item that
+/// started at `offset`
+pub fn locate(e: Error, offset: usize, section: Section, index: usize,
+    expected: &'static str) -> Error
+{
+    let mut p = match e {
+        Error::Parse(p) => p,
+        e => ParseError::new(offset, expected, e)
+    };
+    p.section = section;
+    p.index = index;
+    Error::Parse(p)
+}
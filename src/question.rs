@@ -1,10 +1,12 @@
 use std::fmt;
 use std::str::FromStr;
-use std::io::{Cursor, Write, Read};
-use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+use std::net::IpAddr;
 
-use super::{Name, Error, Type, Class};
+use super::{Name, Compressor, Error, Type, Class, WireReader, WireWriter};
+use super::prelude::*;
 use super::RRType;
+use super::parse_error::field;
+use super::types::PTR;
 
 #[derive(Clone)]
 pub struct Question {
@@ -23,14 +25,20 @@ impl Question {
             qclass: c
         }
     }
+    /// Parse the name from a string.  A name without a trailing dot is
+    /// taken to be relative to the root.
     pub fn new_str<T: RRType>(n: &str, c: Class) -> Result<Self, Error> {
-        Ok(Self::new::<T>(try!(Name::from_str(n)), c))
+        Ok(Self::new::<T>(Name::from_str(n)?.qualify(&Name::root())?, c))
+    }
+    /// A PTR question for the reverse mapping of `addr`
+    pub fn new_reverse(addr: IpAddr) -> Self {
+        Self::new::<PTR>(Name::from_ip(addr), Class::IN)
     }
     pub fn name(&self) -> &Name {
         &self.qname
     }
     pub fn is<T: RRType>(&self) -> bool {
-        T::to_type() == self.qtype
+        T::matches_type(self.qtype)
     }
     pub fn class(&self) -> Class {
         self.qclass
@@ -38,22 +46,22 @@ impl Question {
     pub fn to_string(&self) -> String {
         format!("{:?} {:?} {}", self.qclass, self.qtype, self.qname)
     }
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error> 
-        where Cursor<T> : Write
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        self.serialize_compressed(w, &mut Compressor::disabled())
+    }
+    pub fn serialize_compressed<W: WireWriter + ?Sized>(&self, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        try!(self.qname.serialize(cursor));
-        try!(cursor.write_u16::<BigEndian>(self.qtype.into()));
+        self.qname.serialize_compressed(w, comp)?;
+        w.write_u16(self.qtype.into())?;
         let mut class : u16 = self.qclass.into();
         if self.prefer_unicast { class |= 0x8000u16; }
-        try!(cursor.write_u16::<BigEndian>(class));
-        Ok(())
+        w.write_u16(class)
     }
-    pub fn parse<T>(cursor: &mut Cursor<T>) -> Result<Self, Error> 
-        where Cursor<T> : Read
-    {
-        let n = Name::parse(cursor)?;
-        let qtype = cursor.read_u16::<BigEndian>()?;
-        let qclass = cursor.read_u16::<BigEndian>()?;
+    pub fn parse(r: &mut WireReader) -> Result<Self, Error> {
+        let n = field(r, "question name", Name::parse)?;
+        let qtype = field(r, "question type", |r| r.read_u16())?;
+        let qclass = field(r, "question class", |r| r.read_u16())?;
         Ok(Question {
             qname: n,
             prefer_unicast: qclass & (1 << 15) != 0,
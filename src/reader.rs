@@ -0,0 +1,195 @@
+use super::{Question, ResourceRecord, OptRecord, Error, Type, WireReader};
+use super::rr::ResourceRecordAddl;
+use super::parse_error::{Section, locate};
+use super::view::{skip_name, truncated, label_starts};
+
+const SECTIONS: [Section; 4] = [Section::Question, Section::Answer, Section::Authority,
+    Section::Additional];
+
+/// Reads the records of a message one at a time
+///
+/// Only the record being read is held in memory.  Questions can be read
+/// with `next_question`; iterating skips any that are left and yields the
+/// answer, authority and additional records with their section.  An OPT
+/// record is not yielded but kept for `opt`.  Skipped records are passed
+/// over using RDLENGTH alone.  After an error the reader yields nothing
+/// more, and `position` gives the start of the entry that failed.
+pub struct RecordReader<'a> {
+    reader: WireReader<'a>,
+    counts: [usize; 4],
+    /// Current section, as an index into `SECTIONS`
+    section: usize,
+    /// Index of the next entry within the section
+    index: usize,
+    opt: Option<OptRecord>,
+    done: bool
+}
+
+impl<'a> RecordReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < 12 {
+            return Err(locate(truncated(0, "header"), 0, Section::Header, 0, "header"));
+        }
+        let count = |n: usize| ((data[4 + 2 * n] as usize) << 8) | data[5 + 2 * n] as usize;
+        //found up front, so that names may point into rdata that is skipped
+        let mut wire = WireReader::with_labels(data, label_starts(data));
+        wire.set_position(12);
+        let mut reader = RecordReader {
+            reader: wire,
+            counts: [count(0), count(1), count(2), count(3)],
+            section: 0,
+            index: 0,
+            opt: None,
+            done: false
+        };
+        reader.next_section();
+        Ok(reader)
+    }
+    pub fn id(&self) -> u16 {
+        let data = self.reader.data();
+        (data[0] as u16) << 8 | data[1] as u16
+    }
+    pub fn num_questions(&self) -> usize {
+        self.counts[0]
+    }
+    pub fn num_answers(&self) -> usize {
+        self.counts[1]
+    }
+    pub fn num_authority(&self) -> usize {
+        self.counts[2]
+    }
+    pub fn num_additional(&self) -> usize {
+        self.counts[3]
+    }
+    /// Offset of the next unread octet
+    pub fn position(&self) -> usize {
+        self.reader.position()
+    }
+    /// The section the next entry will be read from, or `None` at the end
+    pub fn section(&self) -> Option<Section> {
+        if self.done {
+            return None;
+        }
+        SECTIONS.get(self.section).cloned()
+    }
+    /// The OPT record, once the reader has got past it
+    pub fn opt(&self) -> Option<&OptRecord> {
+        self.opt.as_ref()
+    }
+    /// Move past empty sections
+    fn next_section(&mut self) {
+        while self.section < 4 && self.index >= self.counts[self.section] {
+            self.section += 1;
+            self.index = 0;
+        }
+        if self.section == 4 {
+            self.done = true;
+        }
+    }
+    /// Record an error in the entry at `start` and stop there
+    fn fail<T>(&mut self, e: Error, start: usize, expected: &'static str) -> Option<Result<T, Error>> {
+        self.done = true;
+        self.reader.set_position(start);
+        Some(Err(locate(e, start, SECTIONS[self.section], self.index, expected)))
+    }
+    /// Read the next question, or `None` once the questions are done
+    pub fn next_question(&mut self) -> Option<Result<Question, Error>> {
+        if self.done || self.section != 0 {
+            return None;
+        }
+        let start = self.reader.position();
+        match Question::parse(&mut self.reader) {
+            Ok(q) => {
+                self.index += 1;
+                self.next_section();
+                Some(Ok(q))
+            }
+            Err(e) => self.fail(e, start, "question")
+        }
+    }
+    /// Check that the entry at the current position fits in the message, and
+    /// give its end and type, without decoding anything
+    fn frame(&self) -> Result<(usize, Type), Error> {
+        let data = self.reader.data();
+        let start = self.reader.position();
+        let pos = skip_name(data, start)?;
+        let fixed = if self.section == 0 { 4 } else { 10 };
+        if pos + fixed > data.len() {
+            return Err(truncated(pos, "record type"));
+        }
+        let t = Type::from((data[pos] as u16) << 8 | data[pos + 1] as u16);
+        if self.section == 0 {
+            return Ok((pos + 4, t));
+        }
+        let end = pos + 10 + ((data[pos + 8] as usize) << 8 | data[pos + 9] as usize);
+        if end > data.len() {
+            return Err(truncated(pos + 10, "rdata"));
+        }
+        Ok((end, t))
+    }
+    /// Move past the next question or record without decoding it, giving
+    /// its section and type
+    pub fn skip_entry(&mut self) -> Option<Result<(Section, Type), Error>> {
+        if self.done {
+            return None;
+        }
+        let start = self.reader.position();
+        match self.frame() {
+            Ok((end, t)) => {
+                self.reader.set_position(end);
+                let section = SECTIONS[self.section];
+                self.index += 1;
+                self.next_section();
+                Some(Ok((section, t)))
+            }
+            Err(e) => self.fail(e, start, "record")
+        }
+    }
+    /// Read the next record whose section and type satisfy `f`, skipping
+    /// the others without decoding them
+    pub fn next_matching<F>(&mut self, f: F) -> Option<Result<(Section, ResourceRecord), Error>>
+        where F: Fn(Section, Type) -> bool
+    {
+        while !self.done {
+            let start = self.reader.position();
+            let (end, t) = match self.frame() {
+                Ok(x) => x,
+                Err(e) => return self.fail(e, start, "record")
+            };
+            let section = SECTIONS[self.section];
+            if section == Section::Question || (t != Type::OPT && !f(section, t)) {
+                self.reader.set_position(end);
+                self.index += 1;
+                self.next_section();
+                continue;
+            }
+            let rr = match ResourceRecord::parse_additional(&mut self.reader) {
+                Ok(rr) => rr,
+                Err(e) => return self.fail(e, start, "record")
+            };
+            //check the OPT record while the section and index still refer to it
+            if let ResourceRecordAddl::OPT(_) = rr {
+                if section != Section::Additional {
+                    return self.fail(Error::InvalidOpt, start, "record");
+                }
+                if self.opt.is_some() {
+                    return self.fail(Error::MultipleOpt, start, "at most one OPT record");
+                }
+            }
+            self.index += 1;
+            self.next_section();
+            match rr {
+                ResourceRecordAddl::RR(rr) => return Some(Ok((section, rr))),
+                ResourceRecordAddl::OPT(opt) => self.opt = Some(opt)
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for RecordReader<'a> {
+    type Item = Result<(Section, ResourceRecord), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_matching(|_, _| true)
+    }
+}
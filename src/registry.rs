@@ -0,0 +1,152 @@
+//! Record types defined outside this crate
+//!
+//! Any `RRType` whose data is `Clone + Display + Send + Sync` can be added
+//! to a `Registry`.  Messages parsed with `Message::parse_with` and that
+//! registry then have records of that type parsed with its `parse`, stored
+//! as `RRData::Registered`, and read back through `ResourceRecord::get`.
+//! Such a type's `map`, `map_mut` and `unmap` should be
+//! `RRData::get_registered`, `RRData::get_registered_mut` and
+//! `RRData::new_registered`.
+
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+
+use alloc::collections::BTreeMap;
+
+use super::{Compressor, Error, RRData, RRType, Type, WireReader, WireWriter};
+use super::prelude::*;
+
+/// Rdata of a registered type, with the operations the type provides
+trait Rdata: Any + Send + Sync {
+    fn typecode(&self) -> u16;
+    fn clone_box(&self) -> Box<dyn Rdata>;
+    fn serialize(&self, w: &mut dyn WireWriter, comp: &mut Compressor) -> Result<(), Error>;
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct Wrapped<T: RRType>(T::D, PhantomData<fn() -> T>);
+
+impl<T> Rdata for Wrapped<T>
+    where T: RRType + 'static, T::D: Clone + fmt::Display + Send + Sync + 'static
+{
+    fn typecode(&self) -> u16 {
+        T::to_type().into()
+    }
+    fn clone_box(&self) -> Box<dyn Rdata> {
+        Box::new(Wrapped::<T>(self.0.clone(), PhantomData))
+    }
+    fn serialize(&self, w: &mut dyn WireWriter, comp: &mut Compressor) -> Result<(), Error> {
+        T::serialize_compressed(&self.0, w, comp)
+    }
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Rdata of a type added to a `Registry`
+pub struct RegisteredRecord {
+    data: Box<dyn Rdata>
+}
+
+impl RegisteredRecord {
+    pub fn new<T>(d: T::D) -> Self
+        where T: RRType + 'static, T::D: Clone + fmt::Display + Send + Sync + 'static
+    {
+        RegisteredRecord { data: Box::new(Wrapped::<T>(d, PhantomData)) }
+    }
+    pub fn typecode(&self) -> u16 {
+        self.data.typecode()
+    }
+    pub fn get<T: RRType + 'static>(&self) -> Option<&T::D> {
+        self.data.as_any().downcast_ref::<Wrapped<T>>().map(|x| &x.0)
+    }
+    pub fn get_mut<T: RRType + 'static>(&mut self) -> Option<&mut T::D> {
+        self.data.as_any_mut().downcast_mut::<Wrapped<T>>().map(|x| &mut x.0)
+    }
+    pub fn serialize_compressed<W: WireWriter + ?Sized>(&self, mut w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
+    {
+        self.data.serialize(&mut w, comp)
+    }
+}
+
+impl Clone for RegisteredRecord {
+    fn clone(&self) -> Self {
+        RegisteredRecord { data: self.data.clone_box() }
+    }
+}
+
+impl fmt::Display for RegisteredRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.data.fmt(f)
+    }
+}
+
+impl RRData {
+    /// Wrap data of a registered type; for use as `RRType::unmap`
+    pub fn new_registered<T>(d: T::D) -> RRData
+        where T: RRType + 'static, T::D: Clone + fmt::Display + Send + Sync + 'static
+    {
+        RRData::Registered(RegisteredRecord::new::<T>(d))
+    }
+    /// The data if it is of registered type `T`; for use as `RRType::map`
+    pub fn get_registered<T: RRType + 'static>(&self) -> Option<&T::D> {
+        match *self {
+            RRData::Registered(ref x) => x.get::<T>(),
+            _ => None
+        }
+    }
+    pub fn get_registered_mut<T: RRType + 'static>(&mut self) -> Option<&mut T::D> {
+        match *self {
+            RRData::Registered(ref mut x) => x.get_mut::<T>(),
+            _ => None
+        }
+    }
+}
+
+pub type ParseFn = for<'a, 'b> fn(&'a mut WireReader<'b>, u16) -> Result<RRData, Error>;
+
+/// Record types defined outside the crate, for `Message::parse_with`
+#[derive(Clone, Default)]
+pub struct Registry {
+    parsers: BTreeMap<u16, ParseFn>
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry { parsers: BTreeMap::new() }
+    }
+    /// Decode records of type `T::to_type()` with `T`.  Fails with
+    /// `TypeRegistered` if the crate already handles the type or another
+    /// type was registered for it.
+    pub fn register<T>(&mut self) -> Result<(), Error>
+        where T: RRType + 'static, T::D: Clone + fmt::Display + Send + Sync + 'static
+    {
+        let t = T::to_type();
+        let typecode : u16 = t.into();
+        if RRData::is_builtin(t) || self.parsers.contains_key(&typecode) {
+            return Err(Error::TypeRegistered(typecode));
+        }
+        self.parsers.insert(typecode, T::parse_data);
+        Ok(())
+    }
+    /// The parser registered for records of type `t`, if any
+    pub fn lookup(&self, t: Type) -> Option<ParseFn> {
+        self.parsers.get(&t.into()).cloned()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.parsers.keys()).finish()
+    }
+}
@@ -1,10 +1,12 @@
 use std::fmt;
 use std::str::FromStr;
-use std::io::{Cursor, Write, Read};
 use std::net::{Ipv4Addr, Ipv6Addr};
-use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
 
-use super::{Name, Error, Class, Type};
+use super::{Name, Compressor, Error, Class, Type, EdnsOption, WireReader, WireWriter};
+use super::prelude::*;
+use super::edns::EdnsOptions;
+use super::registry::{Registry, RegisteredRecord};
+use super::parse_error::field;
 
 #[derive(Clone)]
 pub struct ResourceRecord {
@@ -49,6 +51,18 @@ pub struct MxRecord {
     pub exchange: Name
 }
 
+/// A `<character-string>`: up to 255 octets of arbitrary data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharacterString {
+    data: Vec<u8>
+}
+
+/// TXT rdata: one or more character-strings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxtRecord {
+    pub strings: Vec<CharacterString>
+}
+
 #[derive(Clone)]
 pub struct UnknownRecord {
     pub typecode: u16,
@@ -65,12 +79,19 @@ pub enum RRData {
     SOA(SoaRecord),
     PTR(Name),
     MX(MxRecord),
-    TXT(Vec<u8>),
-    Unknown(UnknownRecord)
+    TXT(TxtRecord),
+    Unknown(UnknownRecord),
+    /// A type added to a `Registry`
+    Registered(RegisteredRecord)
 }
 
+/// Call `$func` on the type that handles `$t`, or give `$other` if the crate
+/// has none
 macro_rules! map_rrtype {
     ($t:ident, $func:ident $call:tt) => {
+        map_rrtype!($t, $func $call, super::types::Unknown::$func $call)
+    };
+    ($t:ident, $func:ident $call:tt, $other:expr) => {
         match $t {
             Type::A => super::types::A::$func $call,
             Type::AAAA => super::types::AAAA::$func $call,
@@ -81,7 +102,7 @@ macro_rules! map_rrtype {
             Type::SOA => super::types::SOA::$func $call,
             Type::SRV => super::types::SRV::$func $call,
             Type::TXT => super::types::TXT::$func $call,
-            _ => super::types::Unknown::$func $call
+            _ => $other
         }
     }
 }
@@ -95,19 +116,25 @@ pub trait RRType {
     fn to_type_data(_: &RRData) -> Result<Type, Error> {
         Ok(Self::to_type())
     }
-    fn serialize<T>(&Self::D, &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T> : Write;
-    fn parse<T>(&mut Cursor<T>, u16) -> Result<Self::D, Error>
-        where Cursor<T> : Read;
-    fn serialize_data<T>(d: &RRData, c: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T> : Write
-    {
-        Self::serialize(Self::map(d).ok_or(Error::ParserStateError)?, c)
+    /// Whether records of type `t` are represented by this type
+    fn matches_type(t: Type) -> bool {
+        t == Self::to_type()
     }
-    fn parse_data<T>(c: &mut Cursor<T>, l: u16) -> Result<RRData, Error>
-        where Cursor<T> : Read
+    fn serialize<W: WireWriter + ?Sized>(d: &Self::D, w: &mut W) -> Result<(), Error>;
+    /// Serialize with name compression.  Only types whose rdata names may
+    /// be compressed (RFC 3597 section 4) need to override this.
+    fn serialize_compressed<W: WireWriter + ?Sized>(d: &Self::D, w: &mut W, _: &mut Compressor)
+        -> Result<(), Error>
     {
-        Self::parse(c, l).map(Self::unmap)
+        Self::serialize(d, w)
+    }
+    /// Parse `len` octets of rdata.  The reader ends where the rdata does.
+    fn parse(r: &mut WireReader, len: u16) -> Result<Self::D, Error>;
+    fn serialize_data<W: WireWriter + ?Sized>(d: &RRData, w: &mut W) -> Result<(), Error> {
+        Self::serialize(Self::map(d).ok_or(Error::ParserStateError)?, w)
+    }
+    fn parse_data(r: &mut WireReader, l: u16) -> Result<RRData, Error> {
+        Self::parse(r, l).map(Self::unmap)
     }
 }
 
@@ -124,17 +151,18 @@ pub enum ResourceRecordAddl {
 
 impl ResourceRecord {
     pub fn get_type(&self) -> Type {
-        match &self.data {
-            &RRData::CNAME(_) => Type::CNAME,
-            &RRData::NS(_) => Type::NS,
-            &RRData::A(_) => Type::A,
-            &RRData::AAAA(_) => Type::AAAA,
-            &RRData::SRV(_) => Type::SRV,
-            &RRData::SOA(_) => Type::SOA,
-            &RRData::PTR(_) => Type::PTR,
-            &RRData::MX(_) => Type::MX,
-            &RRData::TXT(_) => Type::TXT,
-            &RRData::Unknown(ref x) => return Type::Unknown(x.typecode)
+        match self.data {
+            RRData::CNAME(_) => Type::CNAME,
+            RRData::NS(_) => Type::NS,
+            RRData::A(_) => Type::A,
+            RRData::AAAA(_) => Type::AAAA,
+            RRData::SRV(_) => Type::SRV,
+            RRData::SOA(_) => Type::SOA,
+            RRData::PTR(_) => Type::PTR,
+            RRData::MX(_) => Type::MX,
+            RRData::TXT(_) => Type::TXT,
+            RRData::Unknown(ref x) => Type::Unknown(x.typecode),
+            RRData::Registered(ref x) => Type::from(x.typecode())
         }
     }
     pub fn name(&self) -> &Name { 
@@ -165,6 +193,8 @@ impl ResourceRecord {
             data: T::unmap(d)
         }
     }
+    /// Parse the name and data from strings.  Names without a trailing dot,
+    /// in the data too, are taken to be relative to the root.
     pub fn new_str<T>(n: &str, c: Class, d: &str)
         -> Result<Self, RRError<<<T as RRType>::D as FromStr>::Err>>
         where T: RRType, T::D: FromStr
@@ -176,26 +206,27 @@ impl ResourceRecord {
         -> Result<Self, RRError<<<T as RRType>::D as FromStr>::Err>>
         where T: RRType, T::D: FromStr
     {
-        let rrname = try!(Name::from_str(n).map_err(RRError::DNS));
-        let data = try!(T::D::from_str(d).map_err(RRError::DataConv));
-        Ok(Self::new_ttl::<T>(rrname, ttl, c, data))
+        let root = Name::root();
+        let rrname = Name::from_str(n).and_then(|n| n.qualify(&root)).map_err(RRError::DNS)?;
+        let data = T::D::from_str(d).map_err(RRError::DataConv)?;
+        let mut rr = Self::new_ttl::<T>(rrname, ttl, c, data);
+        rr.data.qualify(&root).map_err(RRError::DNS)?;
+        Ok(rr)
     }
-    pub fn parse<T>(cursor: &mut Cursor<T>) -> Result<Self, Error>
-        where Cursor<T> : Read
-    {
-        let n = Name::parse(cursor)?;
-        let t = Type::from(cursor.read_u16::<BigEndian>()?);
-        if t == Type::OPT {
-            //this shouldn't be here!
-            return Err(Error::InvalidOpt);
-        }
-        let c = cursor.read_u16::<BigEndian>()?;
-        let ttl = cursor.read_u32::<BigEndian>()?;
-        let datalen = cursor.read_u16::<BigEndian>()?;
-        let mut data = map_rrtype!(t, parse_data(cursor, datalen))?;
-        if let RRData::Unknown(ref mut x) = data {
-            x.typecode = t.into();
-        }
+    pub fn parse(r: &mut WireReader) -> Result<Self, Error> {
+        let n = field(r, "record name", Name::parse)?;
+        let t = field(r, "record type", |r| {
+            let t = Type::from(r.read_u16()?);
+            if t == Type::OPT {
+                //this shouldn't be here!
+                return Err(Error::InvalidOpt);
+            }
+            Ok(t)
+        })?;
+        let c = field(r, "record class", |r| r.read_u16())?;
+        let ttl = field(r, "record TTL", |r| r.read_u32())?;
+        let datalen = field(r, "RDLENGTH", |r| r.read_u16())?;
+        let data = field(r, "rdata", |r| RRData::parse_window(t, r, datalen as usize))?;
         Ok(ResourceRecord {
             rrname: n,
             multicast_unique: c & (1 << 15) != 0,
@@ -204,25 +235,20 @@ impl ResourceRecord {
             data: data
         })
     }
-    pub fn parse_additional<T>(cursor: &mut Cursor<T>) -> Result<ResourceRecordAddl, Error>
-        where Cursor<T> : Read
-    {
-        let pos = cursor.position();
-        let dat = cursor.read_u32::<BigEndian>()?;
+    pub fn parse_additional(r: &mut WireReader) -> Result<ResourceRecordAddl, Error> {
+        let pos = r.position();
+        let dat = field(r, "record", |r| r.read_u32())?;
         let opt_t : u16 = Type::OPT.into();
         if dat >> 8 != opt_t as u32 {
             //first byte must be 00, second two bytes must be OPT type code
-            cursor.set_position(pos);
-            return Ok(ResourceRecordAddl::RR(Self::parse(cursor)?));
+            r.set_position(pos);
+            return Ok(ResourceRecordAddl::RR(Self::parse(r)?));
         }
-        let mut udp = (dat & 0xFF) as u16;
-        udp = (udp << 8) | (cursor.read_u8()? as u16);
-        let extrcode = cursor.read_u8()?;
-        let version = cursor.read_u8()?;
-        let flags = cursor.read_u16::<BigEndian>()?;
-        let datalen = cursor.read_u16::<BigEndian>()?;
-        let mut buf = vec![0u8; datalen as usize];
-        cursor.read_exact(&mut buf[..])?;
+        let (udp, extrcode, version, flags, datalen) = field(r, "OPT record", |r| {
+            let udp = ((dat & 0xFF) as u16) << 8 | r.read_u8()? as u16;
+            Ok((udp, r.read_u8()?, r.read_u8()?, r.read_u16()?, r.read_u16()?))
+        })?;
+        let buf = field(r, "rdata", |r| r.read_bytes(datalen as usize))?.to_vec();
         Ok(ResourceRecordAddl::OPT(OptRecord{
             udp: udp,
             extrcode: extrcode,
@@ -231,98 +257,414 @@ impl ResourceRecord {
             data: buf
         }))
     }
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error> 
-        where Cursor<T> : Write
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        self.serialize_compressed(w, &mut Compressor::disabled())
+    }
+    pub fn serialize_compressed<W: WireWriter + ?Sized>(&self, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        try!(self.rrname.serialize(cursor));
-        try!(cursor.write_u16::<BigEndian>(self.get_type().into()));
+        self.rrname.serialize_compressed(w, comp)?;
+        w.write_u16(self.get_type().into())?;
         let mut rrclass : u16 = self.rrclass.into();
         if self.multicast_unique { rrclass |= 0x8000; }
-        try!(cursor.write_u16::<BigEndian>(rrclass));
-        try!(cursor.write_u32::<BigEndian>(self.ttl));
-        try!(self.data.serialize(cursor));
-        Ok(())
+        w.write_u16(rrclass)?;
+        w.write_u32(self.ttl)?;
+        self.data.serialize_compressed(w, comp)
+    }
+}
+
+impl ResourceRecord {
+    /// Write the record as a master file line: `owner TTL CLASS TYPE RDATA`.
+    /// Names within `origin` are written relative to it.
+    pub fn fmt_presentation(&self, f: &mut fmt::Formatter, origin: Option<&Name>)
+        -> fmt::Result
+    {
+        write!(f, "{} {} {} {} ", self.rrname.to_presentation(origin), self.ttl,
+            self.rrclass, self.get_type())?;
+        self.data.fmt_presentation(f, origin)
     }
 }
 
 impl fmt::Display for ResourceRecord {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        //FIXME: optionally include TTL
-        match &self.data {
-            &RRData::CNAME(ref n) => write!(f, "{} {:?} CNAME {}", self.rrname,
-                self.rrclass, n),
-            &RRData::NS(ref n) => write!(f, "{} {:?} NS {}", self.rrname,
-                self.rrclass, n),
-            &RRData::A(ref a) => write!(f, "{} {:?} A {}", self.rrname,
-                self.rrclass, a),
-            &RRData::AAAA(ref a) => write!(f, "{} {:?} AAAA {}", self.rrname,
-                self.rrclass, a),
-            &RRData::SRV(ref rec) => write!(f, "{} {:?} SRV {} {} {} {}",
-                self.rrname, self.rrclass, rec.priority, rec.weight,
-                rec.port, rec.target),
-            &RRData::SOA(ref rec) => write!(f,
-                "{} {:?} SOA {} {} ({} {} {} {} {})", self.rrname,
-                self.rrclass, rec.primary_ns, rec.mailbox, rec.serial,
-                rec.refresh, rec.retry, rec.expire, rec.min_ttl),
-            &RRData::PTR(ref n) => write!(f, "{} {:?} PTR {}", self.rrname,
-                self.rrclass, n),
-            &RRData::MX(ref rec) => write!(f, "{} {:?} MX {} {}", self.rrname,
-                self.rrclass, rec.preference, rec.exchange),
-            &RRData::TXT(ref v) => write!(f, "{} {:?} TXT \"{}\"", self.rrname,
-                self.rrclass, String::from_utf8_lossy(&v[..])),
-            &RRData::Unknown(ref v) => {
-                write!(f, "{} {:?} <UNKNOWN> [", self.rrname, self.rrclass)?;
-                let mut first = true;
-                for byte in &v.data {
-                    if !first { write!(f, ", ")?; }
-                    else { first = false; }
-                    write!(f, "{:X}", byte)?;
-                }
-                write!(f, "]")
+        self.fmt_presentation(f, None)
+    }
+}
+
+/// Write rdata in the RFC 3597 generic form, `\# length hex`
+fn fmt_generic(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    write!(f, "\\# {}", data.len())?;
+    if !data.is_empty() {
+        write!(f, " ")?;
+    }
+    for byte in data {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+impl RRData {
+    /// Write the rdata in master file form, with names within `origin`
+    /// written relative to it
+    pub fn fmt_presentation(&self, f: &mut fmt::Formatter, origin: Option<&Name>)
+        -> fmt::Result
+    {
+        match *self {
+            RRData::CNAME(ref n) | RRData::NS(ref n) | RRData::PTR(ref n) =>
+                write!(f, "{}", n.to_presentation(origin)),
+            RRData::A(ref a) => write!(f, "{}", a),
+            RRData::AAAA(ref a) => write!(f, "{}", a),
+            RRData::SRV(ref rec) => write!(f, "{} {} {} {}", rec.priority,
+                rec.weight, rec.port, rec.target.to_presentation(origin)),
+            RRData::SOA(ref rec) => write!(f, "{} {} {} {} {} {} {}",
+                rec.primary_ns.to_presentation(origin),
+                rec.mailbox.to_presentation(origin), rec.serial, rec.refresh,
+                rec.retry, rec.expire, rec.min_ttl),
+            RRData::MX(ref rec) => write!(f, "{} {}", rec.preference,
+                rec.exchange.to_presentation(origin)),
+            RRData::TXT(ref v) => write!(f, "{}", v),
+            RRData::Unknown(ref v) => fmt_generic(f, &v.data[..]),
+            RRData::Registered(ref v) => write!(f, "{}", v)
+        }
+    }
+    /// Append `origin` to any relative names in the rdata.  Names inside
+    /// registered types are left alone.
+    pub fn qualify(&mut self, origin: &Name) -> Result<(), Error> {
+        match *self {
+            RRData::CNAME(ref mut n) | RRData::NS(ref mut n) | RRData::PTR(ref mut n) =>
+                *n = n.qualify(origin)?,
+            RRData::SRV(ref mut rec) => rec.target = rec.target.qualify(origin)?,
+            RRData::SOA(ref mut rec) => {
+                rec.primary_ns = rec.primary_ns.qualify(origin)?;
+                rec.mailbox = rec.mailbox.qualify(origin)?;
             }
+            RRData::MX(ref mut rec) => rec.exchange = rec.exchange.qualify(origin)?,
+            _ => {}
         }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RRData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_presentation(f, None)
     }
 }
 
 impl OptRecord {
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T> : Write
-    {
-        cursor.write_u8(0)?;
-        cursor.write_u16::<BigEndian>(41)?; //OPT magic number
-        cursor.write_u16::<BigEndian>(self.udp)?;
-        cursor.write_u8(self.extrcode)?;
-        cursor.write_u8(self.version)?;
-        cursor.write_u16::<BigEndian>(self.flags)?;
-        cursor.write_all(&self.data[..])?;
+    pub fn new(udp: u16) -> Self {
+        OptRecord {
+            udp,
+            extrcode: 0,
+            version: 0,
+            flags: 0,
+            data: Vec::new()
+        }
+    }
+    /// Iterate over the EDNS options in the record's data
+    pub fn options(&self) -> EdnsOptions<'_> {
+        EdnsOptions::new(&self.data[..])
+    }
+    /// The first option with the given code, if any
+    pub fn get_option(&self, code: u16) -> Option<EdnsOption> {
+        self.options().filter_map(Result::ok).find(|o| o.code() == code)
+    }
+    /// Append an option to the record's data
+    pub fn add_option(&mut self, opt: &EdnsOption) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        opt.serialize(&mut buf)?;
+        self.data.extend_from_slice(&buf[..]);
         Ok(())
     }
+    /// Remove every option with the given code, leaving the others
+    /// untouched.  Returns the number of options removed.
+    pub fn remove_option(&mut self, code: u16) -> Result<usize, Error> {
+        let mut kept = Vec::with_capacity(self.data.len());
+        let mut removed = 0;
+        {
+            let mut opts = self.options();
+            while let Some(opt) = opts.next_raw() {
+                let (c, data) = opt?;
+                if c == code {
+                    removed += 1;
+                    continue;
+                }
+                kept.write_u16(c)?;
+                kept.write_u16(data.len() as u16)?;
+                kept.extend_from_slice(data);
+            }
+        }
+        self.data = kept;
+        Ok(removed)
+    }
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        if self.data.len() > 0xFFFF {
+            return Err(Error::InvalidOpt);
+        }
+        w.write_u8(0)?; //owner is always the root
+        w.write_u16(Type::OPT.into())?;
+        w.write_u16(self.udp)?;
+        w.write_u8(self.extrcode)?;
+        w.write_u8(self.version)?;
+        w.write_u16(self.flags)?;
+        w.write_u16(self.data.len() as u16)?;
+        w.write_bytes(&self.data[..])
+    }
+}
+
+impl CharacterString {
+    pub fn new(data: Vec<u8>) -> Result<Self, Error> {
+        if data.len() > 255 {
+            return Err(Error::StringTooLong);
+        }
+        Ok(CharacterString { data })
+    }
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        Self::new(data.to_vec())
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..]
+    }
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl FromStr for CharacterString {
+    type Err = Error;
+    /// Parse the presentation form, with or without surrounding quotes,
+    /// decoding `\X` and `\DDD` escapes
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let s = if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+            &s[1..s.len() - 1]
+        }
+        else {
+            s
+        };
+        let bytes = s.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'\\' {
+                out.push(bytes[i]);
+                i += 1;
+                continue;
+            }
+            if i + 1 >= bytes.len() {
+                return Err(Error::InvalidEscape);
+            }
+            if bytes[i + 1].is_ascii_digit() {
+                let digits = s.get(i + 1..i + 4).unwrap_or("");
+                match digits.parse::<u8>() {
+                    Ok(x) if digits.len() == 3 => out.push(x),
+                    _ => return Err(Error::InvalidEscape)
+                }
+                i += 4;
+            }
+            else {
+                out.push(bytes[i + 1]);
+                i += 2;
+            }
+        }
+        Self::new(out)
+    }
+}
+
+impl fmt::Display for CharacterString {
+    /// Quoted, with anything unprintable escaped
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"")?;
+        for &c in &self.data {
+            match c {
+                b'"' | b'\\' => write!(f, "\\{}", c as char)?,
+                0x20..=0x7e => write!(f, "{}", c as char)?,
+                _ => write!(f, "\\{:03}", c)?
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+impl TxtRecord {
+    /// Fails with `RdataTooLong` if the strings don't fit in one record
+    pub fn new(strings: Vec<CharacterString>) -> Result<Self, Error> {
+        let len = strings.iter().fold(0, |n, s| n + 1 + s.len());
+        if len > 0xFFFF {
+            return Err(Error::RdataTooLong);
+        }
+        Ok(TxtRecord { strings })
+    }
+    /// Split data into as many strings as it takes, as publishers of long
+    /// SPF and DKIM records do.  The record holds a little under 64KiB.
+    pub fn from_joined(data: &[u8]) -> Result<Self, Error> {
+        let mut strings : Vec<CharacterString> = data.chunks(255)
+            .map(|c| CharacterString { data: c.to_vec() })
+            .collect();
+        if strings.is_empty() {
+            strings.push(CharacterString { data: Vec::new() });
+        }
+        Self::new(strings)
+    }
+    /// The strings concatenated without separators, which is how SPF
+    /// (RFC 7208 section 3.3) and DKIM (RFC 6376 section 3.6.2.2) read them
+    pub fn joined(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for s in &self.strings {
+            out.extend_from_slice(s.as_bytes());
+        }
+        out
+    }
+}
+
+impl FromStr for TxtRecord {
+    type Err = Error;
+    /// Parse whitespace-separated, optionally quoted character-strings
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut strings = Vec::new();
+        let mut chars = s.char_indices().peekable();
+        while let Some(&(start, c)) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+            let quoted = c == '"';
+            if quoted { chars.next(); }
+            let mut end = s.len();
+            let mut closed = !quoted;
+            while let Some((i, c)) = chars.next() {
+                if c == '\\' {
+                    chars.next();
+                }
+                else if quoted && c == '"' {
+                    end = i + 1;
+                    closed = true;
+                    break;
+                }
+                else if !quoted && c.is_whitespace() {
+                    end = i;
+                    break;
+                }
+            }
+            if !closed {
+                return Err(Error::InvalidEscape);
+            }
+            strings.push(CharacterString::from_str(&s[start..end])?);
+        }
+        if strings.is_empty() {
+            return Err(Error::InvalidEscape);
+        }
+        TxtRecord::new(strings)
+    }
+}
+
+impl fmt::Display for TxtRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, s) in self.strings.iter().enumerate() {
+            if i != 0 { write!(f, " ")?; }
+            write!(f, "{}", s)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the names are in rdata of type `t`: how many fixed octets come
+/// first, and how many names follow them
+pub fn rdata_names(t: Type) -> (usize, usize) {
+    match t {
+        Type::CNAME | Type::NS | Type::PTR => (0, 1),
+        Type::MX => (2, 1),
+        Type::SRV => (6, 1),
+        Type::SOA => (0, 2),
+        _ => (0, 0)
+    }
 }
 
 impl RRData {
-    pub fn serialize<T>(&self, cursor: &mut Cursor<T>) -> Result<(), Error> 
-        where Cursor<T> : Write
+    /// Decode uncompressed rdata of type `t` from a standalone buffer, such
+    /// as the RFC 3597 generic encoding in a zone file
+    pub fn from_wire(t: Type, data: &[u8]) -> Result<RRData, Error> {
+        if t == Type::OPT {
+            return Err(Error::InvalidOpt);
+        }
+        if data.len() > 0xFFFF {
+            return Err(Error::RdataOverrun);
+        }
+        Self::parse_window(t, &mut WireReader::new(data), data.len())
+    }
+    /// Like `from_wire`, but decoding the types in `registry` too
+    pub fn from_wire_with(t: Type, data: &[u8], registry: &Registry) -> Result<RRData, Error> {
+        if t == Type::OPT {
+            return Err(Error::InvalidOpt);
+        }
+        if data.len() > 0xFFFF {
+            return Err(Error::RdataOverrun);
+        }
+        Self::parse_window(t, &mut WireReader::with_registry(data, registry), data.len())
+    }
+    /// Whether the crate has its own representation for records of type `t`
+    pub fn is_builtin(t: Type) -> bool {
+        t == Type::OPT || map_rrtype!(t, matches_type(t), false)
+    }
+    /// Parse the `len` octets of rdata at the reader's position.  The parser
+    /// only sees the message up to the end of the rdata, so it can follow
+    /// compression pointers but can't read past RDLENGTH, and it must use
+    /// every octet.
+    fn parse_window(t: Type, r: &mut WireReader, len: usize) -> Result<RRData, Error> {
+        let end = r.position() + len;
+        if end > r.data().len() {
+            //the message itself is truncated
+            return Err(Error::UnexpectedEnd);
+        }
+        let parsed = r.with_limit(end, |r| {
+            let parsed = match r.registry().and_then(|x| x.lookup(t)) {
+                Some(parse) => parse(r, len as u16),
+                None => map_rrtype!(t, parse_data(r, len as u16))
+            };
+            match parsed {
+                Err(Error::UnexpectedEnd) => Err(Error::RdataOverrun),
+                Ok(_) if r.remaining() != 0 => Err(Error::TrailingRdata(r.remaining())),
+                x => x
+            }
+        });
+        let mut rrdata = parsed?;
+        if let RRData::Unknown(ref mut x) = rrdata {
+            x.typecode = t.into();
+        }
+        Ok(rrdata)
+    }
+    pub fn serialize<W: WireWriter + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        self.serialize_compressed(w, &mut Compressor::disabled())
+    }
+    pub fn serialize_compressed<W: WireWriter + ?Sized>(&self, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
         use super::types::*;
-        try!(cursor.write_u16::<BigEndian>(0));
-        let pos = cursor.position();
-        match self {
-            &RRData::CNAME(ref x) => CNAME::serialize(x, cursor)?,
-            &RRData::NS(ref x) => NS::serialize(x, cursor)?,
-            &RRData::A(ref x) => A::serialize(x, cursor)?,
-            &RRData::AAAA(ref x) => AAAA::serialize(x, cursor)?,
-            &RRData::SRV(ref x) => SRV::serialize(x, cursor)?,
-            &RRData::SOA(ref x) => SOA::serialize(x, cursor)?,
-            &RRData::PTR(ref x) => PTR::serialize(x, cursor)?,
-            &RRData::MX(ref x) => MX::serialize(x, cursor)?,
-            &RRData::TXT(ref x) => cursor.write_all(&x[..])?,
-            &RRData::Unknown(ref x) => cursor.write_all(&x.data[..])?
+        w.write_u16(0)?;
+        let pos = w.position();
+        match *self {
+            RRData::CNAME(ref x) => CNAME::serialize_compressed(x, w, comp)?,
+            RRData::NS(ref x) => NS::serialize_compressed(x, w, comp)?,
+            RRData::A(ref x) => A::serialize(x, w)?,
+            RRData::AAAA(ref x) => AAAA::serialize(x, w)?,
+            RRData::SRV(ref x) => SRV::serialize_compressed(x, w, comp)?,
+            RRData::SOA(ref x) => SOA::serialize_compressed(x, w, comp)?,
+            RRData::PTR(ref x) => PTR::serialize_compressed(x, w, comp)?,
+            RRData::MX(ref x) => MX::serialize_compressed(x, w, comp)?,
+            RRData::TXT(ref x) => TXT::serialize(x, w)?,
+            RRData::Unknown(ref x) => w.write_bytes(&x.data[..])?,
+            RRData::Registered(ref x) => x.serialize_compressed(w, comp)?
         }
-        let endpos = cursor.position();
-        cursor.set_position(pos-2);
-        cursor.write_u16::<BigEndian>((endpos - pos) as u16)?;
-        cursor.set_position(endpos);
-        Ok(())
+        let len = w.position() - pos;
+        if len > 0xFFFF {
+            return Err(Error::RdataTooLong);
+        }
+        w.patch_u16(pos - 2, len as u16)
     }
 }
 
@@ -0,0 +1,98 @@
+use OptRecord;
+use EdnsOption;
+use edns::{self, ClientSubnet, Cookie, ExtendedError};
+use prelude::*;
+
+use std::net::{IpAddr, Ipv4Addr};
+
+fn empty_opt() -> OptRecord {
+    OptRecord { udp: 4096, extrcode: 0, version: 0, flags: 0, data: Vec::new() }
+}
+
+#[test]
+fn edns_options_roundtrip() {
+    let data = b"\x00\x03\x00\x04ns01\
+                 \x00\x08\x00\x07\x00\x01\x18\x00\xc0\x00\x02\
+                 \x00\x0a\x00\x10\x01\x02\x03\x04\x05\x06\x07\x08\
+                 \x11\x12\x13\x14\x15\x16\x17\x18\
+                 \x00\x0b\x00\x00\
+                 \x00\x0c\x00\x03\x00\x00\x00\
+                 \x00\x0f\x00\x06\x00\x12abcd\
+                 \x00\x05\x00\x03\x08\x0d\x0e\
+                 \xfd\xe9\x00\x02\xbe\xef\
+                 \x00\x0c\x00\x01\x01";
+    let mut opt = empty_opt();
+    opt.data = data.to_vec();
+    let opts = opt.options().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(opts.len(), 9);
+    match opts[0] { EdnsOption::Nsid(ref d) => assert_eq!(&d[..], b"ns01"), _ => panic!() }
+    match opts[1] {
+        EdnsOption::ClientSubnet(ref ecs) => {
+            assert_eq!(ecs.source_prefix, 24);
+            assert_eq!(ecs.scope_prefix, 0);
+            assert_eq!(ecs.address, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 0)));
+        }
+        _ => panic!()
+    }
+    match opts[2] { EdnsOption::Cookie(ref c) => assert_eq!(c.server.len(), 8), _ => panic!() }
+    match opts[3] { EdnsOption::TcpKeepalive(None) => (), _ => panic!() }
+    match opts[4] { EdnsOption::Padding(3) => (), _ => panic!() }
+    match opts[5] {
+        EdnsOption::ExtendedError(ref e) => {
+            assert_eq!(e.info_code, 18);
+            assert_eq!(&e.extra_text[..], "abcd");
+        }
+        _ => panic!()
+    }
+    match opts[6] { EdnsOption::Dau(ref d) => assert_eq!(&d[..], &[8, 13, 14]), _ => panic!() }
+    match opts[7] { EdnsOption::Unknown(65001, _) => (), _ => panic!() }
+    //non-zero padding can't be represented, so it is kept verbatim
+    match opts[8] { EdnsOption::Unknown(edns::PADDING, _) => (), _ => panic!() }
+
+    let mut rebuilt = empty_opt();
+    for o in opts.iter() {
+        rebuilt.add_option(o).unwrap();
+    }
+    assert_eq!(&rebuilt.data[..], &data[..]);
+}
+
+#[test]
+fn edns_add_remove_option() {
+    let mut opt = empty_opt();
+    opt.add_option(&EdnsOption::Cookie(Cookie { client: [1; 8], server: Vec::new() })).unwrap();
+    opt.add_option(&EdnsOption::Padding(4)).unwrap();
+    opt.add_option(&EdnsOption::ExtendedError(ExtendedError {
+        info_code: 0, extra_text: String::new() })).unwrap();
+    opt.add_option(&EdnsOption::Padding(2)).unwrap();
+    assert_eq!(opt.remove_option(edns::PADDING).unwrap(), 2);
+    assert_eq!(&opt.data[..], b"\x00\x0a\x00\x08\x01\x01\x01\x01\x01\x01\x01\x01\
+                                 \x00\x0f\x00\x02\x00\x00");
+    assert!(opt.get_option(edns::PADDING).is_none());
+    assert!(opt.get_option(edns::COOKIE).is_some());
+}
+
+#[test]
+fn edns_client_subnet_masks_address() {
+    let mut opt = empty_opt();
+    opt.add_option(&EdnsOption::ClientSubnet(ClientSubnet {
+        source_prefix: 20,
+        scope_prefix: 0,
+        address: IpAddr::V4(Ipv4Addr::new(198, 51, 100, 77))
+    })).unwrap();
+    assert_eq!(&opt.data[..], b"\x00\x08\x00\x07\x00\x01\x14\x00\xc6\x33\x60");
+    assert!(opt.add_option(&EdnsOption::ClientSubnet(ClientSubnet {
+        source_prefix: 33,
+        scope_prefix: 0,
+        address: IpAddr::V4(Ipv4Addr::new(198, 51, 100, 77))
+    })).is_err());
+}
+
+#[test]
+fn edns_malformed_option_data() {
+    let mut opt = empty_opt();
+    opt.data = b"\x00\x0a\x00\x10\x01\x02".to_vec();
+    let mut opts = opt.options();
+    assert!(opts.next().unwrap().is_err());
+    assert!(opts.next().is_none());
+    assert!(opt.remove_option(edns::COOKIE).is_err());
+}
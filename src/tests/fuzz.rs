@@ -0,0 +1,111 @@
+use {Message, MessageRef, RecordReader, Question, Type, Class, ResponseCode, Opcode};
+use types::*;
+use prelude::*;
+
+/// Small deterministic generator so failures are reproducible
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn seeds() -> Vec<Vec<u8>> {
+    vec![
+        b"\x06%\x81\x80\x00\x01\x00\x01\x00\x00\x00\x00\
+          \x07example\x03com\x00\x00\x01\x00\x01\
+          \xc0\x0c\x00\x01\x00\x01\x00\x00\x04\xf8\
+          \x00\x04]\xb8\xd8\"".to_vec(),
+        b"\x9f\xc5\x85\x83\x00\x01\x00\x00\x00\x01\x00\x00\
+          \x0edlkfjkdjdslfkj\x07youtube\x03com\x00\x00\x01\x00\x01\
+          \xc0\x1b\x00\x06\x00\x01\x00\x00\x2a\x30\x00\x1e\xc0\x1b\
+          \x05admin\xc0\x1b\x77\xed\x2a\x73\x00\x00\x51\x80\x00\x00\
+          \x0e\x10\x00\x00\x3a\x80\x00\x00\x2a\x30".to_vec(),
+        b"[\xd9\x81\x80\x00\x01\x00\x01\x00\x00\x00\x01\
+          \x0c_xmpp-server\x04_tcp\x05gmail\x03com\x00\x00!\x00\x01\
+          \xc0\x0c\x00!\x00\x01\x00\x00\x03\x84\x00 \x00\x05\x00\x00\
+          \x14\x95\x0bxmpp-server\x01l\x06google\x03com\x00\
+          \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x0c\
+          \x00\x0a\x00\x08\x01\x02\x03\x04\x05\x06\x07\x08".to_vec(),
+    ]
+}
+
+fn exercise(data: &[u8]) {
+    if let Ok(message) = Message::parse(data) {
+        let _ = message.to_string();
+        let _ = message.response_code();
+        let _ = message.serialize();
+        let _ = message.serialize_with_limit(512);
+    }
+    if let Ok(view) = MessageRef::parse(data) {
+        let _ = view.response_code();
+        for q in view.questions() {
+            let _ = q.name().labels().count();
+        }
+        for rr in view.answers().chain(view.authority()).chain(view.additional()) {
+            let _ = rr.name().to_string();
+            let _ = rr.to_record();
+        }
+    }
+    if let Ok(mut reader) = RecordReader::new(data) {
+        while let Some(Ok(_)) = reader.next_question() {}
+        for _ in reader.by_ref() {}
+        let _ = reader.position();
+    }
+}
+
+#[test]
+fn parse_never_panics_on_random_bytes() {
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+    for _ in 0..20000 {
+        let len = rng.below(64);
+        let data : Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        exercise(&data[..]);
+    }
+}
+
+#[test]
+fn parse_never_panics_on_mutated_packets() {
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    for seed in seeds() {
+        exercise(&seed[..]);
+        for _ in 0..20000 {
+            let mut data = seed.clone();
+            for _ in 0..(1 + rng.below(4)) {
+                let i = rng.below(data.len());
+                data[i] = rng.next() as u8;
+            }
+            if rng.below(4) == 0 {
+                let len = rng.below(data.len());
+                data.truncate(len);
+            }
+            exercise(&data[..]);
+        }
+    }
+}
+
+#[test]
+fn conversions_are_total() {
+    for x in 0..=0xFFFFu16 {
+        let t = Type::from(x);
+        let _ = t.to_string();
+        let c = Class::from(x);
+        let _ = c.to_string();
+        let r = ResponseCode::from(x);
+        let back : u16 = r.into();
+        assert_eq!(ResponseCode::from(back), r);
+        let _ = Opcode::from(x);
+    }
+    let q = Question::new::<Unknown>("example.com".parse().unwrap(), IN);
+    assert!(q.is::<Unknown>());
+    assert!(!q.is::<A>());
+    let q = Question { qtype: Type::Unknown(65280), ..q };
+    assert!(q.is::<Unknown>());
+}
@@ -0,0 +1,96 @@
+use Name;
+use idna::{punycode_encode, punycode_decode, to_ascii};
+
+#[test]
+fn punycode_vectors() {
+    let vectors = [
+        ("bücher", "bcher-kva"),
+        ("münchen", "mnchen-3ya"),
+        ("例え", "r8jz45g"),
+        //RFC 3492 section 7.1 (L)
+        ("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b"),
+        //RFC 3492 section 7.1 (A)
+        ("\u{0644}\u{064A}\u{0647}\u{0645}\u{0627}\u{0628}\u{062A}\u{0643}\u{0644}\
+          \u{0645}\u{0648}\u{0634}\u{0639}\u{0631}\u{0628}\u{064A}\u{061F}", "egbpdaj6bu4bxfgehfvwxn"),
+    ];
+    for &(unicode, ascii) in vectors.iter() {
+        assert_eq!(punycode_encode(unicode).unwrap(), ascii);
+        assert_eq!(punycode_decode(ascii).unwrap(), unicode);
+    }
+    assert!(punycode_decode("ab!c").is_err());
+    assert!(punycode_decode("99999999999").is_err());
+}
+
+#[test]
+fn idna_names() {
+    let name = Name::from_unicode("Bücher.example.").unwrap();
+    assert!(name.is_fqdn());
+    assert_eq!(name.to_string(), "xn--bcher-kva.example.");
+    assert_eq!(name.to_unicode(), "bücher.example.");
+    assert_eq!(to_ascii("ÖBB。at").unwrap(), "xn--bb-eka.at");
+    assert_eq!(to_ascii("www.example.com").unwrap(), "www.example.com");
+    assert_eq!(Name::from_unicode(".").unwrap().to_unicode(), ".");
+    //A-labels are checked and shown in Unicode
+    assert_eq!(Name::from_unicode("XN--BCHER-KVA.example").unwrap().to_unicode(), "bücher.example");
+    assert!(Name::from_unicode("xn--ab!c.example").is_err());
+    assert!(Name::from_unicode("-bücher.example").is_err());
+    //undecodable A-labels are displayed as they are
+    let bad : Name = "xn--99999999999.example".parse().unwrap();
+    assert_eq!(bad.to_unicode(), "xn--99999999999.example");
+}
+
+#[test]
+fn idna_mapping() {
+    assert_eq!(to_ascii("Straße.de").unwrap(), "xn--strae-oqa.de");
+    assert_eq!(to_ascii("ПРИМЕР").unwrap(), "xn--e1afmkfd");
+    assert_eq!(to_ascii("ΣΑΣ").unwrap(), to_ascii("σασ").unwrap());
+    assert_eq!(to_ascii("한국").unwrap(), "xn--3e0b707e");
+    //decomposed and fullwidth forms map to the same A-label
+    assert_eq!(to_ascii("bu\u{308}cher").unwrap(), "xn--bcher-kva");
+    assert_eq!(to_ascii("ｂüｃｈｅｒ").unwrap(), "xn--bcher-kva");
+    assert_eq!(to_ascii("ＥＸＡＭＰＬＥ．ｃｏｍ").unwrap(), "example.com");
+    //ASCII labels are only lowercased
+    assert_eq!(to_ascii("_Xmpp._tcp.r3---sn.bücher").unwrap(), "_xmpp._tcp.r3---sn.xn--bcher-kva");
+    //an escaped dot doesn't split a label
+    assert_eq!(to_ascii("A\\.b.bücher.").unwrap(), "a\\.b.xn--bcher-kva.");
+    assert_eq!(Name::from_unicode("a\\.b.bücher.").unwrap().num_labels(), 2);
+    assert!(to_ascii("bü\\.x").is_err());
+}
+
+#[test]
+fn idna_scripts() {
+    let names = [
+        ("مثال.example.", "xn--mgbh0fb.example."),
+        ("דוגמה.טעסט.", "xn--6dbbec0c.xn--deba0ad."),
+        ("उदाहरण.परीक्षा.", "xn--p1b6ci4b4b3a.xn--11b5bs3a9aj6g."),
+        ("ตัวอย่าง.ไทย.", "xn--72c1a1bt4awk9o.xn--o3cw4h."),
+    ];
+    for &(unicode, ascii) in names.iter() {
+        let name = Name::from_unicode(unicode).unwrap();
+        assert_eq!(name.to_string(), ascii);
+        assert_eq!(name.to_unicode(), unicode);
+    }
+    //ZWJ after a virama is allowed by the contextual rules
+    assert!(to_ascii("\u{0915}\u{094D}\u{200D}\u{0937}").is_ok());
+    let arabic : Name = "xn--egbpdaj6bu4bxfgehfvwxn.example".parse().unwrap();
+    assert_eq!(arabic.to_unicode(), "\u{0644}\u{064A}\u{0647}\u{0645}\u{0627}\u{0628}\u{062A}\
+        \u{0643}\u{0644}\u{0645}\u{0648}\u{0634}\u{0639}\u{0631}\u{0628}\u{064A}\u{061F}.example");
+}
+
+#[test]
+fn idna_invalid() {
+    let rejected = [
+        //ZWJ without a virama before it
+        "ab\u{200D}c",
+        //bidi rule: a right-to-left name can't have a label starting with a
+        //digit, or mix directions within a label
+        "0a.\u{05D0}", "\u{05D0}a", "\u{0661}1",
+        //disallowed characters and a leading combining mark
+        "\u{2488}x", "\u{0300}a",
+        //hyphens (RFC 5891 section 4.2.3.1) and bad A-labels
+        "-bücher", "bü--x", "xn--abc-", "xn--ab!c",
+    ];
+    for name in rejected.iter() {
+        assert!(to_ascii(name).is_err(), "accepted {:?}", name);
+    }
+}
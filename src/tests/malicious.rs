@@ -0,0 +1,167 @@
+use {Message, MessageRef, Error};
+use types::*;
+use prelude::*;
+
+const HEADER: &[u8] = b"\x00\x01\x81\x80\x00\x01\x00\x00\x00\x00\x00\x00";
+
+/// The underlying error, without the parse location
+fn cause(result: Result<Message, Error>) -> Option<Error> {
+    match result {
+        Err(Error::Parse(p)) => Some(*p.error),
+        _ => None
+    }
+}
+
+fn question(name: &[u8]) -> Vec<u8> {
+    let mut packet = HEADER.to_vec();
+    packet.extend_from_slice(name);
+    packet.extend_from_slice(b"\x00\x01\x00\x01");
+    packet
+}
+
+#[test]
+fn malicious_pointers() {
+    let cases : Vec<(&[u8], usize)> = vec![
+        //points at itself
+        (b"\xc0\x0c", 12),
+        //points forward
+        (b"\xc0\x0e\x00", 12),
+        //points into the header
+        (b"\xc0\x02", 12),
+        //points past the end of the message
+        (b"\xff\xff", 12),
+        //a label, then a pointer back to that label
+        (b"\x01a\xc0\x0c", 14),
+    ];
+    for (name, offset) in cases {
+        match cause(Message::parse(&question(name)[..])) {
+            Some(Error::InvalidPointer(x)) => assert_eq!(x, offset),
+            _ => panic!("accepted {:?}", name)
+        }
+    }
+}
+
+#[test]
+fn malicious_pointer_loop_between_records() {
+    //the answer's name points back to itself through the question
+    let mut packet = question(b"\x01a\x00");
+    packet[7] = 1;
+    packet.extend_from_slice(b"\x01b\xc0\x13\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\x7f\x00\x00\x01");
+    match cause(Message::parse(&packet[..])) {
+        Some(Error::InvalidPointer(21)) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn malicious_pointer_into_label() {
+    //the question's only label is "\x02xy", and the answer points at its
+    //second octet, which would read as "xy"
+    let mut packet = question(b"\x03\x02xy\x00");
+    packet[7] = 1;
+    packet.extend_from_slice(b"\xc0\x0d\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\x7f\x00\x00\x01");
+    match cause(Message::parse(&packet[..])) {
+        Some(Error::InvalidPointer(21)) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn malicious_pointer_into_rdata() {
+    //the address in the first answer looks like the name "z", and the
+    //second answer points at it
+    let mut packet = answer(b"\x00\x04\x01z\x00\x00");
+    packet[7] = 2;
+    packet.extend_from_slice(b"\xc0\x29\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\x7f\x00\x00\x01");
+    match cause(Message::parse(&packet[..])) {
+        Some(Error::InvalidPointer(45)) => (),
+        _ => panic!()
+    }
+    let view = MessageRef::parse(&packet[..]).unwrap();
+    let second = view.answers().nth(1).unwrap();
+    match second.name().labels().next() {
+        Some(Err(Error::InvalidPointer(45))) => (),
+        _ => panic!()
+    }
+    assert!(second.to_record().is_err());
+}
+
+#[test]
+fn malicious_label_types() {
+    for &(name, offset) in [(&b"\x41a"[..], 12), (&b"\x01a\x81a"[..], 14)].iter() {
+        match cause(Message::parse(&question(name)[..])) {
+            Some(Error::UnknownLabelFormat(x)) => assert_eq!(x, offset),
+            _ => panic!()
+        }
+    }
+}
+
+#[test]
+fn malicious_truncated_names() {
+    for name in [&b"\xc0"[..], b"\x05ab", b"\x01a"].iter() {
+        let mut packet = HEADER.to_vec();
+        packet.extend_from_slice(name);
+        assert!(Message::parse(&packet[..]).is_err());
+    }
+}
+
+#[test]
+fn backward_pointer_chains() {
+    //question a., answer b.a. pointing to it, CNAME c.b.a. pointing to that
+    let mut packet = question(b"\x01a\x00");
+    packet[7] = 1;
+    packet.extend_from_slice(b"\x01b\xc0\x0c\x00\x05\x00\x01\x00\x00\x00\x00\x00\x04\x01c\xc0\x13");
+    let message = Message::parse(&packet[..]).unwrap();
+    let answer = message.get_answer(0).unwrap();
+    assert_eq!(answer.name().to_string(), "b.a.");
+    assert_eq!(answer.get::<CNAME>().unwrap().to_string(), "c.b.a.");
+}
+
+fn answer(rdata: &[u8]) -> Vec<u8> {
+    //question example.com A, then an answer with the given RDLENGTH and rdata
+    let mut packet = question(b"\x07example\x03com\x00");
+    packet[7] = 1;
+    packet.extend_from_slice(b"\xc0\x0c\x00\x01\x00\x01\x00\x00\x04\xf8");
+    packet.extend_from_slice(rdata);
+    packet
+}
+
+#[test]
+fn rdlength_bounds() {
+    assert!(Message::parse(&answer(b"\x00\x04\xc0\x00\x02\x01")[..]).is_ok());
+    //RDLENGTH shorter than an A record
+    match cause(Message::parse(&answer(b"\x00\x03\xc0\x00\x02\x01")[..])) {
+        Some(Error::RdataOverrun) => (),
+        _ => panic!()
+    }
+    //RDLENGTH longer than an A record
+    match cause(Message::parse(&answer(b"\x00\x05\xc0\x00\x02\x01\x00")[..])) {
+        Some(Error::TrailingRdata(1)) => (),
+        _ => panic!()
+    }
+    //RDLENGTH past the end of the message
+    match cause(Message::parse(&answer(b"\x00\x05\xc0\x00\x02\x01")[..])) {
+        Some(Error::UnexpectedEnd) => (),
+        _ => panic!()
+    }
+    //a CNAME whose name runs past RDLENGTH
+    let mut packet = answer(b"\x00\x03\x03www\xc0\x0c");
+    packet[32] = 5;
+    match cause(Message::parse(&packet[..])) {
+        Some(Error::RdataOverrun) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn trailing_message_data() {
+    let mut packet = answer(b"\x00\x04\xc0\x00\x02\x01");
+    packet.push(0);
+    match Message::parse(&packet[..]) {
+        Err(Error::Parse(p)) => {
+            assert_eq!(p.offset, packet.len() - 1);
+            match *p.error { Error::TrailingData(1) => (), _ => panic!() }
+        }
+        _ => panic!()
+    }
+}
@@ -1,3 +1,14 @@
 mod parse;
 mod serialize;
-
+mod edns;
+#[cfg(feature = "std")]
+mod zone;
+mod name;
+#[cfg(feature = "idna")]
+mod idna;
+mod malicious;
+mod fuzz;
+mod view;
+mod reader;
+mod wire;
+mod registry;
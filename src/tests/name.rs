@@ -0,0 +1,236 @@
+use {Name, Label, Message, Error, Compressor, WireReader};
+use prelude::*;
+
+use std::str::FromStr;
+
+#[test]
+fn name_escapes() {
+    let name = Name::from_str("a\\.b.c\\032d.\\\\.example").unwrap();
+    let labels : Vec<&[u8]> = name.iter().map(|l| l.as_bytes()).collect();
+    assert_eq!(labels, vec![&b"a.b"[..], b"c d", b"\\", b"example"]);
+    assert_eq!(name.to_string(), "a\\.b.c\\ d.\\\\.example");
+    assert_eq!(Name::from_str(&name.to_string()).unwrap().to_string(), name.to_string());
+    assert_eq!(Label::from_bytes(b"\x00\xff").unwrap().to_string(), "\\000\\255");
+    assert!(Name::from_str("a\\25").is_err());
+    assert!(Name::from_str("a\\256").is_err());
+    assert!(Name::from_str("a..b").is_err());
+}
+
+#[test]
+fn name_wildcard_and_hostname() {
+    let name = Name::from_str("*.example.com").unwrap();
+    assert!(name.is_wildcard());
+    assert!(!name.is_hostname());
+    assert!(!Name::from_str("www.example.com").unwrap().is_wildcard());
+    assert!(Name::from_str("_sip._tcp.example.com").is_ok());
+    assert!(Name::from_hostname("_sip._tcp.example.com").is_err());
+    assert!(Name::from_hostname("-bad.example.com").is_err());
+    assert!(Name::from_hostname("a/b.example.com").is_err());
+    assert!(Name::from_hostname("www-1.example.com").is_ok());
+}
+
+#[test]
+fn parse_binary_labels() {
+    let response = b"\x00\x01\x81\x80\x00\x01\x00\x00\x00\x00\x00\x00\
+                     \x04a/b\xff\x07example\x03com\x00\x00\x01\x00\x01";
+    let message = Message::parse(response).unwrap();
+    let name = message.get_question(0).unwrap().name();
+    assert_eq!(name.iter().next().unwrap().as_bytes(), b"a/b\xff");
+    assert_eq!(name.to_string(), "a/b\\255.example.com.");
+    assert_eq!(&message.serialize().unwrap()[..], &response[..]);
+}
+
+#[test]
+fn name_root_and_fqdn() {
+    let root = Name::from_str(".").unwrap();
+    assert!(root.is_root());
+    assert_eq!(root.to_string(), ".");
+    assert_eq!(root.to_presentation(None), ".");
+    let name = Name::from_str("example.com.").unwrap();
+    assert!(name.is_fqdn());
+    assert_eq!(name.iter().count(), 2);
+    assert_eq!(name.to_presentation(None), "example.com.");
+    assert_eq!(name.to_string(), "example.com.");
+    assert!(Name::from_str(&name.to_string()).unwrap() == name);
+    assert!(Name::from_str(&root.to_string()).unwrap().is_root());
+    let relative = Name::from_str("www").unwrap();
+    assert!(!relative.is_fqdn());
+    assert_eq!(relative.to_presentation(None), "www");
+    //an escaped dot is part of the label
+    assert!(!Name::from_str("www\\.").unwrap().is_fqdn());
+    assert!(Name::from_str("").is_err());
+    assert!(Name::from_str("example..").is_err());
+    //names read off the wire are always absolute
+    let query = b"\x06%\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+                  \x07example\x03com\x00\x00\x01\x00\x01";
+    assert!(Message::parse(query).unwrap().get_question(0).unwrap().name().is_fqdn());
+}
+
+#[test]
+fn name_relativize_and_qualify() {
+    let origin = Name::from_str("Example.com.").unwrap();
+    let name = Name::from_str("www.sub.example.COM.").unwrap();
+    let relative = name.relativize(&origin).unwrap();
+    assert!(!relative.is_fqdn());
+    assert_eq!(relative.to_string(), "www.sub");
+    let qualified = relative.qualify(&origin).unwrap();
+    assert!(qualified.is_fqdn());
+    assert_eq!(qualified.to_string(), "www.sub.Example.com.");
+    assert_eq!(name.qualify(&Name::from_str("other.").unwrap()).unwrap().to_string(), name.to_string());
+    assert!(origin.relativize(&origin).unwrap().iter().next().is_none());
+    assert!(Name::from_str("example.org.").unwrap().relativize(&origin).is_none());
+    assert!(Name::from_str("www.example.com").unwrap().relativize(&origin).is_none());
+    assert_eq!(name.relativize(&Name::root()).unwrap().to_string(), "www.sub.example.COM");
+}
+
+#[test]
+fn name_serialize_relative() {
+    //a relative name has no root label, so it can't go on the wire as it is
+    let relative = Name::from_str("www.example").unwrap();
+    let mut data = Vec::new();
+    assert!(matches!(relative.serialize(&mut data), Err(Error::RelativeName)));
+    assert!(matches!(relative.serialize_compressed(&mut data, &mut Compressor::new()),
+        Err(Error::RelativeName)));
+    assert!(data.is_empty());
+    let qualified = relative.qualify(&Name::from_str("com.").unwrap()).unwrap();
+    qualified.serialize(&mut data).unwrap();
+    assert_eq!(&data[..], b"\x03www\x07example\x03com\x00");
+    let parsed = Name::parse(&mut WireReader::new(&data[..])).unwrap();
+    assert!(parsed.is_fqdn());
+    assert!(parsed == qualified);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn name_case_insensitive_eq_and_hash() {
+    use std::collections::HashSet;
+    let a = Name::from_str("WWW.Example.com.").unwrap();
+    let b = Name::from_str("www.example.COM.").unwrap();
+    assert!(a == b);
+    assert!(a != Name::from_str("www.example.com").unwrap());
+    assert!(a != Name::from_str("www.example.net.").unwrap());
+    let mut set = HashSet::new();
+    set.insert(a.clone());
+    assert!(set.contains(&b));
+    //the original case is kept for output
+    assert_eq!(a.to_string(), "WWW.Example.com.");
+    assert_eq!(a.to_lowercase().to_string(), "www.example.com.");
+}
+
+#[test]
+fn name_canonical_order() {
+    //the example from RFC 4034 section 6.1
+    let sorted = ["example.", "a.example.", "yljkjljk.a.example.", "Z.a.example.",
+        "zABC.a.EXAMPLE.", "z.example.", "\\001.z.example.", "*.z.example.",
+        "\\200.z.example."];
+    let mut names : Vec<Name> = sorted.iter().rev()
+        .map(|s| Name::from_str(s).unwrap()).collect();
+    names.sort();
+    let names : Vec<String> = names.iter().map(|n| n.to_presentation(None)).collect();
+    assert_eq!(names, sorted.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+}
+
+#[test]
+fn name_hierarchy() {
+    let name = Name::from_str("www.sub.Example.com.").unwrap();
+    let zone = Name::from_str("example.COM.").unwrap();
+    assert_eq!(name.num_labels(), 4);
+    assert_eq!(Name::root().num_labels(), 0);
+    assert_eq!(name.parent().unwrap().to_string(), "sub.Example.com.");
+    assert!(Name::root().parent().is_none());
+    assert!(name.is_subdomain_of(&zone));
+    assert!(zone.is_subdomain_of(&zone));
+    assert!(name.is_subdomain_of(&Name::root()));
+    assert!(!zone.is_subdomain_of(&name));
+    assert!(!Name::from_str("www.example.com").unwrap().is_subdomain_of(&zone));
+    assert_eq!(name.strip_suffix(&zone).unwrap().to_string(), "www.sub");
+    let other = Name::from_str("mail.example.com.").unwrap();
+    assert!(name.common_ancestor(&other) == zone);
+    assert!(name.common_ancestor(&Name::from_str("example.org.").unwrap()).is_root());
+    let ancestors : Vec<String> = name.ancestors().map(|n| n.to_string()).collect();
+    assert_eq!(ancestors, vec!["www.sub.Example.com.", "sub.Example.com.", "Example.com.", "com.", "."]);
+    assert_eq!(Name::from_str("a.b").unwrap().ancestors().count(), 2);
+    let label = Label::from_str("mail").unwrap();
+    assert!(zone.prepend_label(label.clone()).unwrap() == other);
+    let long = Label::from_bytes(&[b'x'; 63]).unwrap();
+    let mut big = Name::root();
+    for _ in 0..3 {
+        big = big.prepend_label(long.clone()).unwrap();
+    }
+    //3 * 64 + 1 octets, so 61 more fit but not 62
+    assert!(big.prepend_label(Label::from_bytes(&[b'y'; 61]).unwrap()).is_ok());
+    assert!(big.prepend_label(Label::from_bytes(&[b'y'; 62]).unwrap()).is_err());
+}
+
+#[test]
+fn name_length_limits() {
+    use Error;
+    let l63 = String::from_utf8(vec![b'a'; 63]).unwrap();
+    let l64 = String::from_utf8(vec![b'a'; 64]).unwrap();
+    assert!(Label::from_str(&l63).is_ok());
+    match Label::from_str(&l64) { Err(Error::LabelTooLong) => (), _ => panic!() }
+    match Label::from_str("") { Err(Error::EmptyLabel) => (), _ => panic!() }
+    assert_eq!(Name::root().wire_len(), 1);
+    assert_eq!(Name::from_str("example.com.").unwrap().wire_len(), 13);
+    //four 63 octet labels and the root take 257 octets
+    let long = format!("{0}.{0}.{0}.{0}", l63);
+    match Name::from_str(&long) { Err(Error::NameTooLong) => (), _ => panic!() }
+    let fits = format!("{0}.{0}.{0}.{1}", l63, &l63[..61]);
+    let mut name = Name::from_str(&fits).unwrap();
+    assert_eq!(name.wire_len(), 255);
+    assert!(name.push(Label::from_str("a").unwrap()).is_err());
+    assert_eq!(name.wire_len(), 255);
+    let relative = Name::from_str(&format!("{0}.{0}.{0}", l63)).unwrap();
+    let origin = Name::from_str(&format!("{}.", &l63[..61])).unwrap();
+    assert!(relative.qualify(&origin).is_ok());
+    let origin = Name::from_str(&format!("{}.", &l63[..62])).unwrap();
+    assert!(relative.qualify(&origin).is_err());
+    //the same too-long name on the wire
+    let mut packet = b"\x00\x01\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00".to_vec();
+    for _ in 0..4 {
+        packet.push(63);
+        packet.extend_from_slice(l63.as_bytes());
+    }
+    packet.extend_from_slice(b"\x00\x00\x01\x00\x01");
+    assert!(Message::parse(&packet[..]).is_err());
+}
+
+#[test]
+fn name_reverse_lookup() {
+    use std::net::{IpAddr, Ipv4Addr};
+    use Question;
+    use types::PTR;
+    let v4 : IpAddr = "192.0.2.1".parse().unwrap();
+    let name = Name::from_ip(v4);
+    assert_eq!(name.to_presentation(None), "1.2.0.192.in-addr.arpa.");
+    assert_eq!(name.to_ip(), Some(v4));
+    let v6 : IpAddr = "2001:db8::567:89ab".parse().unwrap();
+    let name = Name::from_ip(v6);
+    assert_eq!(name.to_presentation(None),
+        "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa.");
+    assert_eq!(name.to_ip(), Some(v6));
+    assert_eq!(Name::from_str("1.2.0.192.IN-ADDR.ARPA.").unwrap().to_ip(), Some(v4));
+    assert!(Name::from_str("2.0.192.in-addr.arpa.").unwrap().to_ip().is_none());
+    assert!(Name::from_str("1.2.0.256.in-addr.arpa.").unwrap().to_ip().is_none());
+    //octets are decimal, without leading zeros
+    for bad in ["01.2.0.192", "1.00.0.192", "1.2.0.1c0", "1.2.0.+92", "1.2.0.0192"].iter() {
+        let name = Name::from_str(&format!("{}.in-addr.arpa.", bad)).unwrap();
+        assert!(name.to_ip().is_none(), "accepted {}", bad);
+    }
+    assert_eq!(Name::from_str("0.0.0.0.in-addr.arpa.").unwrap().to_ip(),
+        Some("0.0.0.0".parse().unwrap()));
+    assert!(Name::from_str("1.2.0.192.in-addr.arpa").unwrap().to_ip().is_none());
+    assert!(Name::from_str("www.example.com.").unwrap().to_ip().is_none());
+
+    let addr = Ipv4Addr::new(192, 0, 2, 65);
+    assert_eq!(Name::classless_zone(addr, 26).unwrap().to_presentation(None),
+        "64/26.2.0.192.in-addr.arpa.");
+    let name = Name::from_ip_classless(addr, 26).unwrap();
+    assert_eq!(name.to_presentation(None), "65.64/26.2.0.192.in-addr.arpa.");
+    assert!(name.to_ip().is_none());
+    assert!(Name::classless_zone(addr, 24).is_err());
+
+    let q = Question::new_reverse(v4);
+    assert!(q.is::<PTR>());
+    assert!(q.name() == &Name::from_ip(v4));
+}
@@ -2,6 +2,7 @@ use Message;
 use Opcode;
 use ResponseCode;
 use types::*;
+use prelude::*;
 
 use std::net::{Ipv4Addr, Ipv6Addr};
 
@@ -24,7 +25,7 @@ fn parse_example_query() {
     assert_eq!(message.num_authority(), 0);
     assert_eq!(message.num_additional(), 0);
     let question = message.get_question(0).unwrap();
-    assert_eq!(&question.name().to_string()[..], "example.com");
+    assert_eq!(&question.name().to_string()[..], "example.com.");
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
 }
@@ -50,11 +51,11 @@ fn parse_example_response() {
     assert_eq!(message.num_authority(), 0);
     assert_eq!(message.num_additional(), 0);
     let question = message.get_question(0).unwrap();
-    assert_eq!(&question.name().to_string()[..], "example.com");
+    assert_eq!(&question.name().to_string()[..], "example.com.");
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
     let answer = message.get_answer(0).unwrap();
-    assert_eq!(&answer.name().to_string()[..], "example.com");
+    assert_eq!(&answer.name().to_string()[..], "example.com.");
     assert_eq!(answer.class(), IN);
     assert_eq!(answer.ttl, 1272); //TODO
     assert_eq!(*answer.get::<A>().unwrap(), Ipv4Addr::new(93, 184, 216, 34));
@@ -97,18 +98,18 @@ fn parse_ns_response() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "www.skype.com");
+    assert_eq!(&question.name().to_string()[..], "www.skype.com.");
     assert_eq!(message.num_answers(), 1);
     let answer = message.get_answer(0).unwrap();
     assert_eq!(answer.class(), IN);
     assert_eq!(answer.ttl, 3600);
-    assert_eq!(&answer.get::<CNAME>().unwrap().to_string()[..], "livecms.trafficmanager.net");
+    assert_eq!(&answer.get::<CNAME>().unwrap().to_string()[..], "livecms.trafficmanager.net.");
     assert_eq!(message.num_authority(), 1);
     let authority = message.get_authority(0).unwrap();
-    assert_eq!(&authority.name().to_string()[..], "net");
+    assert_eq!(&authority.name().to_string()[..], "net.");
     assert_eq!(authority.class(), IN);
     assert_eq!(authority.ttl, 120275);
-    assert_eq!(&authority.get::<NS>().unwrap().to_string()[..], "g.gtld-servers.net");
+    assert_eq!(&authority.get::<NS>().unwrap().to_string()[..], "g.gtld-servers.net.");
 }
 
 #[test]
@@ -132,15 +133,15 @@ fn parse_soa_response() {
     let question = message.get_question(0).unwrap();
     assert_eq!(question.class(), IN);
     assert!(question.is::<A>());
-    assert_eq!(&question.name().to_string()[..], "dlkfjkdjdslfkj.youtube.com");
+    assert_eq!(&question.name().to_string()[..], "dlkfjkdjdslfkj.youtube.com.");
     assert_eq!(message.num_answers(), 0);
     assert_eq!(message.num_authority(), 1);
     let authority = message.get_authority(0).unwrap();
     assert_eq!(authority.class(), IN);
     assert_eq!(authority.ttl, 10800);
     let soa = authority.get::<SOA>().unwrap();
-    assert_eq!(&soa.primary_ns.to_string()[..], "youtube.com");
-    assert_eq!(&soa.mailbox.to_string()[..], "admin.youtube.com");
+    assert_eq!(&soa.primary_ns.to_string()[..], "youtube.com.");
+    assert_eq!(&soa.mailbox.to_string()[..], "admin.youtube.com.");
     assert_eq!(soa.serial, 2012031603);
     assert_eq!(soa.refresh, 20864);
     assert_eq!(soa.retry, 3600);
@@ -169,13 +170,13 @@ fn parse_ptr_response() {
     let question = message.get_question(0).unwrap();
     assert_eq!(question.class(), IN);
     assert!(question.is::<PTR>());
-    assert_eq!(&question.name().to_string()[..], "69.93.75.72.in-addr.arpa");
+    assert_eq!(&question.name().to_string()[..], "69.93.75.72.in-addr.arpa.");
     assert_eq!(message.num_answers(), 1);
     let answer = message.get_answer(0).unwrap();
-    assert_eq!(&answer.name().to_string()[..], "69.93.75.72.in-addr.arpa");
+    assert_eq!(&answer.name().to_string()[..], "69.93.75.72.in-addr.arpa.");
     assert_eq!(answer.class(), IN);
     assert_eq!(answer.ttl, 86400);
-    assert_eq!(&answer.get::<PTR>().unwrap().to_string()[..], "pool-72-75-93-69.verizon.net");
+    assert_eq!(&answer.get::<PTR>().unwrap().to_string()[..], "pool-72-75-93-69.verizon.net.");
     assert_eq!(message.num_authority(), 0);
     assert_eq!(message.num_additional(), 0);
 }
@@ -207,22 +208,22 @@ fn parse_additional_record_response() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "www.skype.com");
+    assert_eq!(&question.name().to_string()[..], "www.skype.com.");
     assert_eq!(message.num_answers(), 1);
     let answer = message.get_answer(0).unwrap();
-    assert_eq!(&answer.name().to_string()[..], "www.skype.com");
+    assert_eq!(&answer.name().to_string()[..], "www.skype.com.");
     assert_eq!(answer.class(), IN);
     assert_eq!(answer.ttl, 3600);
-    assert_eq!(&answer.get::<CNAME>().unwrap().to_string()[..], "livecms.trafficmanager.net");
+    assert_eq!(&answer.get::<CNAME>().unwrap().to_string()[..], "livecms.trafficmanager.net.");
     assert_eq!(message.num_authority(), 1);
     let authority = message.get_authority(0).unwrap();
-    assert_eq!(&authority.name().to_string()[..], "net");
+    assert_eq!(&authority.name().to_string()[..], "net.");
     assert_eq!(authority.class(), IN);
     assert_eq!(authority.ttl, 120275);
-    assert_eq!(&authority.get::<NS>().unwrap().to_string()[..], "g.gtld-servers.net");
+    assert_eq!(&authority.get::<NS>().unwrap().to_string()[..], "g.gtld-servers.net.");
     assert_eq!(message.num_additional(), 1);
     let additional = message.get_additional(0).unwrap();
-    assert_eq!(&additional.name().to_string()[..], "a.gtld-servers.net");
+    assert_eq!(&additional.name().to_string()[..], "a.gtld-servers.net.");
     assert_eq!(additional.class(), IN);
     assert_eq!(additional.ttl, 41756);
     assert_eq!(*additional.get::<A>().unwrap(), Ipv4Addr::new(192, 5, 6, 30));
@@ -254,7 +255,7 @@ fn parse_multiple_answers() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "google.com");
+    assert_eq!(&question.name().to_string()[..], "google.com.");
     assert_eq!(message.num_answers(), 6);
     let ips = vec![
         Ipv4Addr::new(64, 233, 164, 100),
@@ -266,7 +267,7 @@ fn parse_multiple_answers() {
     ];
     for i in 0..6 {
         let answer = message.get_answer(i).unwrap();
-        assert_eq!(&answer.name().to_string()[..], "google.com");
+        assert_eq!(&answer.name().to_string()[..], "google.com.");
         assert_eq!(answer.class(), IN);
         assert_eq!(answer.ttl, 239);
         assert_eq!(*answer.get::<A>().unwrap(), ips[i]);
@@ -292,7 +293,7 @@ fn parse_srv_query() {
     assert_eq!(message.num_authority(), 0);
     assert_eq!(message.num_additional(), 0);
     let question = message.get_question(0).unwrap();
-    assert_eq!(&question.name().to_string()[..], "_xmpp-server._tcp.gmail.com");
+    assert_eq!(&question.name().to_string()[..], "_xmpp-server._tcp.gmail.com.");
     assert!(question.is::<SRV>());
     assert_eq!(question.class(), IN);
 }
@@ -336,18 +337,18 @@ fn parse_srv_response() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<SRV>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "_xmpp-server._tcp.gmail.com");
+    assert_eq!(&question.name().to_string()[..], "_xmpp-server._tcp.gmail.com.");
     assert_eq!(message.num_answers(), 5);
     let items = vec![
-        (5, 0, 5269, "xmpp-server.l.google.com"),
-        (20, 0, 5269, "alt3.xmpp-server.l.google.com"),
-        (20, 0, 5269, "alt1.xmpp-server.l.google.com"),
-        (20, 0, 5269, "alt2.xmpp-server.l.google.com"),
-        (20, 0, 5269, "alt4.xmpp-server.l.google.com"),
+        (5, 0, 5269, "xmpp-server.l.google.com."),
+        (20, 0, 5269, "alt3.xmpp-server.l.google.com."),
+        (20, 0, 5269, "alt1.xmpp-server.l.google.com."),
+        (20, 0, 5269, "alt2.xmpp-server.l.google.com."),
+        (20, 0, 5269, "alt4.xmpp-server.l.google.com."),
     ];
     for i in 0..5 {
         let answer = message.get_answer(i).unwrap();
-        assert_eq!(&answer.name().to_string()[..], "_xmpp-server._tcp.gmail.com");
+        assert_eq!(&answer.name().to_string()[..], "_xmpp-server._tcp.gmail.com.");
         assert_eq!(answer.class(), IN);
         assert_eq!(answer.ttl, 900);
         let srv = answer.get::<SRV>().unwrap();
@@ -382,18 +383,18 @@ fn parse_mx_response() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<MX>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "gmail.com");
+    assert_eq!(&question.name().to_string()[..], "gmail.com.");
     assert_eq!(message.num_answers(), 5);
     let items = vec![
-        ( 5, "gmail-smtp-in.l.google.com"),
-        (10, "alt1.gmail-smtp-in.l.google.com"),
-        (40, "alt4.gmail-smtp-in.l.google.com"),
-        (20, "alt2.gmail-smtp-in.l.google.com"),
-        (30, "alt3.gmail-smtp-in.l.google.com"),
+        ( 5, "gmail-smtp-in.l.google.com."),
+        (10, "alt1.gmail-smtp-in.l.google.com."),
+        (40, "alt4.gmail-smtp-in.l.google.com."),
+        (20, "alt2.gmail-smtp-in.l.google.com."),
+        (30, "alt3.gmail-smtp-in.l.google.com."),
     ];
     for i in 0..5 {
         let answer = message.get_answer(i).unwrap();
-        assert_eq!(&answer.name().to_string()[..], "gmail.com");
+        assert_eq!(&answer.name().to_string()[..], "gmail.com.");
         assert_eq!(answer.class(), IN);
         assert_eq!(answer.ttl, 1148);
         let mx = answer.get::<MX>().unwrap();
@@ -422,11 +423,11 @@ fn parse_aaaa_response() {
     assert_eq!(message.num_authority(), 0);
     assert_eq!(message.num_additional(), 0);
     let question = message.get_question(0).unwrap();
-    assert_eq!(&question.name().to_string()[..], "google.com");
+    assert_eq!(&question.name().to_string()[..], "google.com.");
     assert!(question.is::<AAAA>());
     assert_eq!(question.class(), IN);
     let answer = message.get_answer(0).unwrap();
-    assert_eq!(&answer.name().to_string()[..], "google.com");
+    assert_eq!(&answer.name().to_string()[..], "google.com.");
     assert_eq!(answer.class(), IN);
     assert_eq!(answer.ttl, 139); //TODO
     assert_eq!(*answer.get::<AAAA>().unwrap(),
@@ -461,13 +462,13 @@ fn parse_cname_response() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "cdn.sstatic.net");
+    assert_eq!(&question.name().to_string()[..], "cdn.sstatic.net.");
     assert_eq!(message.num_answers(), 6);
     let answer = message.get_answer(0).unwrap();
-    assert_eq!(&answer.name().to_string()[..], "cdn.sstatic.net");
+    assert_eq!(&answer.name().to_string()[..], "cdn.sstatic.net.");
     assert_eq!(answer.class(), IN);
     assert_eq!(answer.ttl, 102);
-    assert_eq!(&answer.get::<CNAME>().unwrap().to_string()[..], "sstatic.net");
+    assert_eq!(&answer.get::<CNAME>().unwrap().to_string()[..], "sstatic.net.");
     let ips = vec![
         Ipv4Addr::new(104, 16, 103, 204),
         Ipv4Addr::new(104, 16, 107, 204),
@@ -477,7 +478,7 @@ fn parse_cname_response() {
     ];
     for i in 1..6 {
         let answer = message.get_answer(i).unwrap();
-        assert_eq!(&answer.name().to_string()[..], "sstatic.net");
+        assert_eq!(&answer.name().to_string()[..], "sstatic.net.");
         assert_eq!(answer.class(), IN);
         assert_eq!(answer.ttl, 102);
         assert_eq!(*answer.get::<A>().unwrap(), ips[i-1]);
@@ -505,7 +506,7 @@ fn parse_example_query_edns() {
     let question = message.get_question(0).unwrap();
     assert!(question.is::<A>());
     assert_eq!(question.class(), IN);
-    assert_eq!(&question.name().to_string()[..], "google.com");
+    assert_eq!(&question.name().to_string()[..], "google.com.");
     assert_eq!(message.num_answers(), 0);
     let opt = message.opt.as_ref().unwrap();
     assert_eq!(opt.udp, 4096);
@@ -513,3 +514,115 @@ fn parse_example_query_edns() {
     assert_eq!(opt.version, 0);
     assert_eq!(opt.flags, 0);
 }
+
+#[test]
+fn parse_extended_response_code() {
+    let response = b"\x95\xce\x81\x80\x00\x00\x00\x00\x00\x00\x00\x01\
+        \x00\x00\x29\x10\x00\x01\x00\x00\x00\x00\x00";
+    let message = Message::parse(response).unwrap();
+    assert_eq!(message.response_code(), ResponseCode::BadVers);
+    assert_eq!(message.opt.as_ref().unwrap().extrcode, 1);
+}
+
+#[test]
+fn parse_ad_cd_flags() {
+    let response = b"\x06%\x81\xa0\x00\x00\x00\x00\x00\x00\x00\x00";
+    let message = Message::parse(response).unwrap();
+    assert!(message.authenticated_data());
+    assert!(!message.checking_disabled());
+    let query = b"\x06%\x01\x10\x00\x00\x00\x00\x00\x00\x00\x00";
+    let message = Message::parse(query).unwrap();
+    assert!(!message.authenticated_data());
+    assert!(message.checking_disabled());
+}
+
+#[test]
+fn parse_txt_response() {
+    let response = b"\x12\x34\x81\x80\x00\x01\x00\x01\x00\x00\x00\x00\
+        \x07example\x03com\x00\x00\x10\x00\x01\
+        \xc0\x0c\x00\x10\x00\x01\x00\x00\x0e\x10\x00\x13\
+        \x09v=spf1 -a\x00\x07ll\x01\"\\\xffx";
+    let message = Message::parse(response).unwrap();
+    let answer = message.get_answer(0).unwrap();
+    let txt = answer.get::<TXT>().unwrap();
+    assert_eq!(txt.strings.len(), 3);
+    assert_eq!(txt.strings[0].as_bytes(), b"v=spf1 -a");
+    assert!(txt.strings[1].is_empty());
+    assert_eq!(&txt.joined()[..], b"v=spf1 -all\x01\"\\\xffx");
+    assert_eq!(&txt.to_string()[..], "\"v=spf1 -a\" \"\" \"ll\\001\\\"\\\\\\255x\"");
+    assert_eq!(&message.serialize().unwrap()[..], &response[..]);
+}
+
+#[test]
+fn parse_txt_overrun() {
+    use Error;
+    //the last string claims more octets than RDLENGTH allows
+    let response = b"\x12\x34\x81\x80\x00\x00\x00\x01\x00\x00\x00\x00\
+        \x00\x00\x10\x00\x01\x00\x00\x0e\x10\x00\x03\x05abcdefg";
+    match Message::parse(response) {
+        Err(Error::Parse(p)) => assert!(matches!(*p.error, Error::RdataOverrun)),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn parse_txt_empty() {
+    use {Error, RRData, RRType, Type, TxtRecord};
+    use std::str::FromStr;
+    //TXT rdata holds at least one string, so the wire and presentation
+    //forms both reject an empty one
+    let response = b"\x12\x34\x81\x80\x00\x00\x00\x01\x00\x00\x00\x00\
+        \x00\x00\x10\x00\x01\x00\x00\x0e\x10\x00\x00";
+    match Message::parse(response) {
+        Err(Error::Parse(p)) => assert!(matches!(*p.error, Error::RdataOverrun)),
+        _ => panic!()
+    }
+    assert!(RRData::from_wire(Type::TXT, b"").is_err());
+    assert!(TxtRecord::from_str("").is_err());
+    let txt = RRData::from_wire(Type::TXT, b"\x00").unwrap();
+    assert_eq!(TxtRecord::from_str("\"\"").unwrap(), *TXT::map(&txt).unwrap());
+}
+
+#[test]
+fn parse_error_locations() {
+    use {Error, Section};
+    use std::error::Error as StdError;
+    //truncated in the TTL of the second answer
+    let response = b"\x06%\x81\x80\x00\x01\x00\x02\x00\x00\x00\x00\
+                     \x07example\x03com\x00\x00\x01\x00\x01\
+                     \xc0\x0c\x00\x01\x00\x01\x00\x00\x04\xf8\x00\x04]\xb8\xd8\"\
+                     \xc0\x0c\x00\x01\x00\x01\x00\x00";
+    let err = match Message::parse(response) {
+        Err(Error::Parse(p)) => p,
+        _ => panic!()
+    };
+    assert_eq!(err.section, Section::Answer);
+    assert_eq!(err.index, 1);
+    assert_eq!(err.offset, 51);
+    assert_eq!(err.expected, "record TTL");
+    match *err.error {
+        Error::UnexpectedEnd => (),
+        _ => panic!()
+    }
+    assert!(err.source().is_some());
+    assert_eq!(&err.to_string()[..34], "record TTL at offset 51 in answer ");
+    //truncated header
+    match Message::parse(b"\x06%\x81\x80\x00\x01") {
+        Err(Error::Parse(p)) => {
+            assert_eq!(p.section, Section::Header);
+            assert_eq!(p.expected, "header");
+        }
+        _ => panic!()
+    }
+    //a second OPT record
+    let query = b"\x95\xce\x01\x00\x00\x00\x00\x00\x00\x00\x00\x02\
+        \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00";
+    match Message::parse(query) {
+        Err(Error::Parse(p)) => {
+            assert_eq!((p.section, p.index, p.offset), (Section::Additional, 1, 23));
+            match *p.error { Error::MultipleOpt => (), _ => panic!() }
+        }
+        _ => panic!()
+    }
+}
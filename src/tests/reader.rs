@@ -0,0 +1,120 @@
+use {RecordReader, Section, Type, Error};
+use types::*;
+use prelude::*;
+
+const RESPONSE: &[u8] = b"\xe3\xe8\x81\x80\x00\x01\x00\x02\x00\x01\x00\x02\
+    \x05gmail\x03com\x00\x00\x0f\x00\x01\
+    \xc0\x0c\x00\x0f\x00\x01\x00\x00\x04|\x00\x1b\x00\x05\rgmail-smtp-in\x01l\x06google\xc0\x12\
+    \xc0\x0c\x00\x0f\x00\x01\x00\x00\x04|\x00\t\x00\n\x04alt1\xc0)\
+    \xc0\x0c\x00\x02\x00\x01\x00\x00\x04|\x00\x06\x03ns1\xc0\x0c\
+    \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00\
+    \xc0\x63\x00\x01\x00\x01\x00\x00\x04|\x00\x04\xc0\x00\x02\x01";
+
+#[test]
+fn reader_yields_records_with_sections() {
+    let mut reader = RecordReader::new(RESPONSE).unwrap();
+    assert_eq!(reader.num_questions(), 1);
+    assert_eq!(reader.num_answers(), 2);
+    assert_eq!(reader.section(), Some(Section::Question));
+    let q = reader.next_question().unwrap().unwrap();
+    assert_eq!(q.name().to_string(), "gmail.com.");
+    assert!(reader.next_question().is_none());
+    assert!(reader.opt().is_none());
+    let records : Vec<_> = reader.by_ref().map(|r| r.unwrap()).collect();
+    let sections : Vec<Section> = records.iter().map(|&(s, _)| s).collect();
+    assert_eq!(sections, vec![Section::Answer, Section::Answer, Section::Authority,
+        Section::Additional]);
+    assert_eq!(records[2].1.get::<NS>().unwrap().to_string(), "ns1.gmail.com.");
+    assert_eq!(records[3].1.name().to_string(), "ns1.gmail.com.");
+    assert_eq!(reader.opt().unwrap().udp, 4096);
+    assert_eq!(reader.position(), RESPONSE.len());
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn reader_skips_without_decoding() {
+    //iterating skips the questions
+    assert_eq!(RecordReader::new(RESPONSE).unwrap().count(), 4);
+    let mut reader = RecordReader::new(RESPONSE).unwrap();
+    assert_eq!(reader.skip_entry().unwrap().unwrap(), (Section::Question, Type::MX));
+    assert_eq!(reader.skip_entry().unwrap().unwrap(), (Section::Answer, Type::MX));
+    let (section, rr) = reader.next_matching(|_, t| t == Type::A).unwrap().unwrap();
+    assert_eq!(section, Section::Additional);
+    assert!(rr.is::<A>());
+    assert!(reader.opt().is_some());
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn reader_stops_on_error() {
+    //cut off in the middle of the authority record
+    let data = &RESPONSE[..95];
+    let mut reader = RecordReader::new(data).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_ok());
+    match reader.next() {
+        Some(Err(Error::Parse(p))) => {
+            assert_eq!((p.section, p.index), (Section::Authority, 0));
+        }
+        _ => panic!()
+    }
+    assert_eq!(reader.section(), None);
+    assert!(reader.next().is_none());
+    assert_eq!(reader.position(), 87);
+    assert!(RecordReader::new(&RESPONSE[..5]).is_err());
+}
+
+#[test]
+fn reader_rejects_misplaced_opt() {
+    //an OPT record as the only answer
+    let data = b"\x00\x01\x81\x80\x00\x00\x00\x01\x00\x00\x00\x00\
+        \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00";
+    let mut reader = RecordReader::new(data).unwrap();
+    match reader.next() {
+        Some(Err(Error::Parse(p))) => {
+            assert_eq!((p.section, p.index, p.offset), (Section::Answer, 0, 12));
+            assert!(matches!(*p.error, Error::InvalidOpt));
+        }
+        _ => panic!()
+    }
+    assert_eq!(reader.position(), 12);
+    assert!(reader.next().is_none());
+    //a second OPT record at the end of the additional section
+    let data = b"\x00\x01\x81\x80\x00\x00\x00\x00\x00\x00\x00\x02\
+        \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00";
+    let mut reader = RecordReader::new(data).unwrap();
+    match reader.next() {
+        Some(Err(Error::Parse(p))) => {
+            assert_eq!((p.section, p.index, p.offset), (Section::Additional, 1, 23));
+            assert!(matches!(*p.error, Error::MultipleOpt));
+        }
+        _ => panic!()
+    }
+    assert_eq!(reader.position(), 23);
+    assert!(reader.opt().is_some());
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn reader_skips_by_rdlength() {
+    //a CNAME answer whose rdata has a bad label type, then an A answer
+    let data = b"\x00\x01\x81\x80\x00\x00\x00\x02\x00\x00\x00\x00\
+        \x00\x00\x05\x00\x01\x00\x00\x00\x00\x00\x02\x80\x00\
+        \x00\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\xc0\x00\x02\x01";
+    let mut reader = RecordReader::new(data).unwrap();
+    assert_eq!(reader.skip_entry().unwrap().unwrap(), (Section::Answer, Type::CNAME));
+    assert_eq!(reader.position(), 25);
+    let (_, rr) = reader.next().unwrap().unwrap();
+    assert!(rr.is::<A>());
+    let mut reader = RecordReader::new(data).unwrap();
+    let (_, rr) = reader.next_matching(|_, t| t == Type::A).unwrap().unwrap();
+    assert!(rr.is::<A>());
+    //decoding the CNAME fails, and the reader stays at its start
+    let mut reader = RecordReader::new(data).unwrap();
+    match reader.next() {
+        Some(Err(Error::Parse(p))) => assert_eq!((p.section, p.index), (Section::Answer, 0)),
+        _ => panic!()
+    }
+    assert_eq!(reader.position(), 12);
+}
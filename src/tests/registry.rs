@@ -0,0 +1,89 @@
+use {Message, ResourceRecord, RRData, RRType, Name, Type, Error, Compressor};
+use {WireReader, WireWriter, Registry};
+use types::*;
+use prelude::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A made-up type from the private use range (RFC 6895 section 3.1)
+struct LOC2;
+
+#[derive(Clone)]
+struct Locator {
+    preference: u16,
+    target: Name
+}
+
+impl fmt::Display for Locator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.preference, self.target.to_presentation(None))
+    }
+}
+
+impl RRType for LOC2 {
+    type D = Locator;
+    fn map(rrd: &RRData) -> Option<&Locator> {
+        rrd.get_registered::<Self>()
+    }
+    fn map_mut(rrd: &mut RRData) -> Option<&mut Locator> {
+        rrd.get_registered_mut::<Self>()
+    }
+    fn unmap(loc: Locator) -> RRData {
+        RRData::new_registered::<Self>(loc)
+    }
+    fn to_type() -> Type {
+        Type::from(65400)
+    }
+    fn parse(r: &mut WireReader, _: u16) -> Result<Locator, Error> {
+        Ok(Locator { preference: r.read_u16()?, target: Name::parse(r)? })
+    }
+    fn serialize<W: WireWriter + ?Sized>(loc: &Locator, w: &mut W) -> Result<(), Error> {
+        w.write_u16(loc.preference)?;
+        loc.target.serialize(w)
+    }
+    fn serialize_compressed<W: WireWriter + ?Sized>(loc: &Locator, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
+    {
+        w.write_u16(loc.preference)?;
+        loc.target.serialize_recorded(w, comp)
+    }
+}
+
+#[test]
+fn registered_type_roundtrip() {
+    let mut registry = Registry::new();
+    registry.register::<LOC2>().unwrap();
+    assert!(matches!(registry.register::<LOC2>(), Err(Error::TypeRegistered(65400))));
+    assert!(matches!(registry.register::<A>(), Err(Error::TypeRegistered(1))));
+    assert!(RRData::is_builtin(Type::OPT));
+    assert!(!RRData::is_builtin(LOC2::to_type()));
+
+    let mut message = Message::new_response(9);
+    let loc = Locator { preference: 10, target: Name::from_str("host.example.com.").unwrap() };
+    message.add_answer(ResourceRecord::new::<LOC2>(
+        Name::from_str("example.com.").unwrap(), IN, loc));
+    let data = message.serialize().unwrap();
+    //the rdata is the preference and the uncompressed target
+    assert_eq!(&data[data.len() - 20..], b"\x00\x0a\x04host\x07example\x03com\x00");
+
+    //without the registry the record stays undecoded
+    let plain = Message::parse(&data[..]).unwrap();
+    assert!(plain.get_answer(0).unwrap().is::<Unknown>());
+
+    let parsed = Message::parse_with(&registry, &data[..]).unwrap();
+    let mut rr = parsed.get_answer(0).unwrap().clone();
+    assert!(rr.is::<LOC2>());
+    assert!(!rr.is::<Unknown>());
+    assert_eq!(rr.get_type(), Type::Unknown(65400));
+    assert_eq!(rr.get::<LOC2>().unwrap().preference, 10);
+    assert_eq!(&rr.data.to_string()[..], "10 host.example.com.");
+
+    let copy = rr.clone();
+    rr.get_mut::<LOC2>().unwrap().preference = 20;
+    assert_eq!(copy.get::<LOC2>().unwrap().preference, 10);
+    assert_eq!(rr.get::<LOC2>().unwrap().preference, 20);
+
+    let rdata = RRData::from_wire_with(LOC2::to_type(), b"\x00\x05\x00", &registry).unwrap();
+    assert_eq!(&rdata.to_string()[..], "5 .");
+}
@@ -0,0 +1,275 @@
+use Message;
+use ResourceRecord;
+use Question;
+use OptRecord;
+use ResponseCode;
+use Opcode;
+use Error;
+use types::*;
+use prelude::*;
+
+use std::net::Ipv4Addr;
+
+#[test]
+fn serialize_compressed_roundtrip() {
+    let packets : Vec<&[u8]> = vec![
+        b"\x06%\x81\x80\x00\x01\x00\x01\x00\x00\x00\x00\
+          \x07example\x03com\x00\x00\x01\x00\x01\
+          \xc0\x0c\x00\x01\x00\x01\x00\x00\x04\xf8\
+          \x00\x04]\xb8\xd8\"",
+        b"\x4a\xf0\x81\x80\x00\x01\x00\x01\x00\x01\x00\x00\
+          \x03www\x05skype\x03com\x00\x00\x01\x00\x01\
+          \xc0\x0c\x00\x05\x00\x01\x00\x00\x0e\x10\
+          \x00\x1c\x07\x6c\x69\x76\x65\x63\x6d\x73\x0e\x74\
+          \x72\x61\x66\x66\x69\x63\x6d\x61\x6e\x61\x67\x65\
+          \x72\x03\x6e\x65\x74\x00\
+          \xc0\x42\x00\x02\x00\x01\x00\x01\xd5\xd3\x00\x11\
+          \x01\x67\x0c\x67\x74\x6c\x64\x2d\x73\x65\x72\x76\x65\x72\x73\
+          \xc0\x42",
+        b"\x9f\xc5\x85\x83\x00\x01\x00\x00\x00\x01\x00\x00\
+          \x0edlkfjkdjdslfkj\x07youtube\x03com\x00\x00\x01\x00\x01\
+          \xc0\x1b\x00\x06\x00\x01\x00\x00\x2a\x30\x00\x1e\xc0\x1b\
+          \x05admin\xc0\x1b\x77\xed\x2a\x73\x00\x00\x51\x80\x00\x00\
+          \x0e\x10\x00\x00\x3a\x80\x00\x00\x2a\x30",
+        b"\xe3\xe8\x81\x80\x00\x01\x00\x05\x00\x00\x00\x00\
+          \x05gmail\x03com\x00\x00\x0f\x00\x01\xc0\x0c\x00\x0f\x00\x01\
+          \x00\x00\x04|\x00\x1b\x00\x05\rgmail-smtp-in\x01l\x06google\xc0\
+          \x12\xc0\x0c\x00\x0f\x00\x01\x00\x00\x04|\x00\t\x00\
+          \n\x04alt1\xc0)\xc0\x0c\x00\x0f\x00\x01\x00\x00\x04|\
+          \x00\t\x00(\x04alt4\xc0)\xc0\x0c\x00\x0f\x00\x01\x00\
+          \x00\x04|\x00\t\x00\x14\x04alt2\xc0)\xc0\x0c\x00\x0f\
+          \x00\x01\x00\x00\x04|\x00\t\x00\x1e\x04alt3\xc0)",
+        b"[\xd9\x81\x80\x00\x01\x00\x02\x00\x00\x00\x00\
+          \x0c_xmpp-server\x04_tcp\x05gmail\x03com\x00\x00!\x00\x01\
+          \xc0\x0c\x00!\x00\x01\x00\x00\x03\x84\x00 \x00\x05\x00\x00\
+          \x14\x95\x0bxmpp-server\x01l\x06google\x03com\x00\xc0\x0c\x00!\
+          \x00\x01\x00\x00\x03\x84\x00%\x00\x14\x00\x00\x14\x95\
+          \x04alt3\x0bxmpp-server\x01l\x06google\x03com\x00",
+    ];
+    for packet in packets {
+        let message = Message::parse(packet).unwrap();
+        assert_eq!(&message.serialize().unwrap()[..], packet);
+    }
+}
+
+#[test]
+fn serialize_compression_is_case_insensitive() {
+    let mut message = Message::new_response(1);
+    message.add_question(Question::new_str::<A>("Example.COM", IN).unwrap());
+    message.add_answer(ResourceRecord::new::<A>(
+        "example.com.".parse().unwrap(), IN, Ipv4Addr::new(192, 0, 2, 1)));
+    let data = message.serialize().unwrap();
+    //header, question, then a pointer back to the question name
+    assert_eq!(&data[29..31], b"\xc0\x0c");
+    assert_eq!(data.len(), 12 + 17 + 16);
+    let parsed = Message::parse(&data[..]).unwrap();
+    assert_eq!(&parsed.get_answer(0).unwrap().name().to_string()[..], "Example.COM.");
+}
+
+#[test]
+fn serialize_single_records_uncompressed() {
+    let rr = ResourceRecord::new_str::<CNAME>("www.example.com", IN, "example.com").unwrap();
+    //undotted names are taken to be relative to the root
+    assert!(rr.name().is_fqdn());
+    assert!(rr.get::<CNAME>().unwrap().is_fqdn());
+    let mut data = Vec::new();
+    rr.serialize(&mut data).unwrap();
+    assert_eq!(&data[data.len() - 13..], b"\x07example\x03com\x00");
+}
+
+fn big_response() -> Message {
+    let mut message = Message::new_response(7);
+    message.add_question(Question::new_str::<A>("www.example.com", IN).unwrap());
+    for i in 0..4 {
+        message.add_answer(ResourceRecord::new_str::<A>("www.example.com", IN,
+            &format!("192.0.2.{}", i)).unwrap());
+    }
+    for i in 0..4 {
+        message.add_authority(ResourceRecord::new_str::<NS>("example.com", IN,
+            &format!("ns{}.example.com", i)).unwrap());
+    }
+    for i in 0..4 {
+        message.add_additional(ResourceRecord::new_str::<A>(
+            &format!("ns{}.example.com", i), IN, &format!("198.51.100.{}", i)).unwrap());
+    }
+    message
+}
+
+#[test]
+fn serialize_with_limit_fits() {
+    let message = big_response();
+    let full = message.serialize().unwrap();
+    assert_eq!(message.serialize_with_limit(full.len()).unwrap(), full);
+    assert!(!Message::parse(&full[..]).unwrap().is_truncated());
+}
+
+#[test]
+fn serialize_with_limit_drops_whole_rrsets() {
+    let message = big_response();
+    //room for the question and the answer RRset, but not the NS RRset
+    let data = message.serialize_with_limit(12 + 21 + 4 * 16 + 10).unwrap();
+    let parsed = Message::parse(&data[..]).unwrap();
+    assert!(parsed.is_truncated());
+    assert_eq!(parsed.num_questions(), 1);
+    assert_eq!(parsed.num_answers(), 4);
+    assert_eq!(parsed.num_authority(), 0);
+    assert_eq!(parsed.num_additional(), 0);
+}
+
+#[test]
+fn serialize_with_limit_additional_does_not_set_tc() {
+    let message = big_response();
+    let full = message.serialize().unwrap();
+    let data = message.serialize_with_limit(full.len() - 1).unwrap();
+    let parsed = Message::parse(&data[..]).unwrap();
+    assert!(!parsed.is_truncated());
+    assert_eq!(parsed.num_authority(), 4);
+    //each additional record is its own RRset, so only the last is dropped
+    assert_eq!(parsed.num_additional(), 3);
+}
+
+#[test]
+fn serialize_with_limit_too_small() {
+    assert!(big_response().serialize_with_limit(11).is_err());
+}
+
+#[test]
+fn serialize_edns_roundtrip() {
+    let query = b"\x95\xce\x01\x00\x00\x01\x00\x00\x00\x00\x00\x01\
+        \x06google\x03com\x00\x00\x01\x00\
+        \x01\x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x0c\
+        \x00\x0a\x00\x08\x01\x02\x03\x04\x05\x06\x07\x08";
+    let message = Message::parse(query).unwrap();
+    assert_eq!(&message.serialize().unwrap()[..], &query[..]);
+}
+
+#[test]
+fn serialize_with_limit_respects_opt_udp() {
+    let mut message = big_response();
+    for i in 0..96 {
+        message.add_answer(ResourceRecord::new_str::<A>("www.example.com", IN,
+            &format!("203.0.113.{}", i)).unwrap());
+    }
+    message.opt = Some(OptRecord::new(1232));
+    assert!(message.serialize().unwrap().len() > 1232);
+    let data = message.serialize_with_limit(4096).unwrap();
+    assert!(data.len() <= 1232);
+    let parsed = Message::parse(&data[..]).unwrap();
+    assert!(parsed.is_truncated());
+    assert_eq!(parsed.num_answers(), 0);
+    assert_eq!(parsed.opt.as_ref().unwrap().udp, 1232);
+    //advertised sizes below 512 are treated as 512
+    let mut message = big_response();
+    message.opt = Some(OptRecord::new(100));
+    let data = message.serialize_with_limit(4096).unwrap();
+    assert!(data.len() > 100);
+    assert!(!Message::parse(&data[..]).unwrap().is_truncated());
+}
+
+#[test]
+fn serialize_extended_response_code() {
+    let mut message = Message::new_error(3, ResponseCode::BadCookie).unwrap();
+    assert_eq!(message.opt.as_ref().unwrap().extrcode, 1);
+    let parsed = Message::parse(&message.serialize().unwrap()[..]).unwrap();
+    assert_eq!(parsed.response_code(), ResponseCode::BadCookie);
+    message.set_response_code(ResponseCode::Refused).unwrap();
+    assert_eq!(message.opt.as_ref().unwrap().extrcode, 0);
+    let parsed = Message::parse(&message.serialize().unwrap()[..]).unwrap();
+    assert_eq!(parsed.response_code(), ResponseCode::Refused);
+}
+
+#[test]
+fn serialize_bad_sig_reads_as_bad_vers() {
+    let message = Message::new_error(3, ResponseCode::BadSig).unwrap();
+    let parsed = Message::parse(&message.serialize().unwrap()[..]).unwrap();
+    assert_eq!(parsed.response_code(), ResponseCode::BadVers);
+}
+
+#[test]
+fn set_response_code_past_12_bits() {
+    let mut message = Message::new_response(3);
+    assert!(matches!(message.set_response_code(ResponseCode::Reserved(0x1000)),
+        Err(Error::InvalidResponseCode(0x1000))));
+    assert!(message.opt.is_none());
+    assert_eq!(message.response_code(), ResponseCode::NoError);
+    assert!(matches!(Message::new_error(3, ResponseCode::Reserved(0xFFFF)),
+        Err(Error::InvalidResponseCode(0xFFFF))));
+}
+
+#[test]
+fn serialize_header_flags() {
+    let mut message = Message::new_query(9);
+    message.set_authenticated_data(true);
+    message.set_checking_disabled(true);
+    assert_eq!(&message.serialize().unwrap()[2..4], b"\x01\x30");
+    message.set_authenticated_data(false);
+    assert_eq!(&message.serialize().unwrap()[2..4], b"\x01\x10");
+    //the reserved Z bit survives a round trip
+    let query = b"\x06%\x01\x70\x00\x00\x00\x00\x00\x00\x00\x00";
+    assert_eq!(&Message::parse(query).unwrap().serialize().unwrap()[..], &query[..]);
+}
+
+#[test]
+fn serialize_opcodes() {
+    let mut message = Message::new_query(9);
+    message.set_opcode(Opcode::Notify);
+    assert_eq!(&message.serialize().unwrap()[2..4], b"\x21\x00");
+    message.set_opcode(Opcode::Update);
+    assert_eq!(&message.serialize().unwrap()[2..4], b"\x29\x00");
+    message.set_opcode(Opcode::Reserved(15));
+    let parsed = Message::parse(&message.serialize().unwrap()[..]).unwrap();
+    assert_eq!(parsed.opcode(), Opcode::Reserved(15));
+    message.set_opcode(Opcode::Reserved(16));
+    assert!(message.serialize().is_err());
+    assert!(Opcode::from_code(16).is_err());
+    assert_eq!(Opcode::from_code(6).unwrap(), Opcode::Dso);
+}
+
+#[test]
+fn txt_presentation() {
+    use {TxtRecord, CharacterString};
+    use std::str::FromStr;
+    let txt = TxtRecord::from_str("\"a \\\"b\\\"\"  plain \\065\\066 \"\"").unwrap();
+    assert_eq!(txt.strings.len(), 4);
+    assert_eq!(txt.strings[0].as_bytes(), b"a \"b\"");
+    assert_eq!(txt.strings[1].as_bytes(), b"plain");
+    assert_eq!(txt.strings[2].as_bytes(), b"AB");
+    assert!(txt.strings[3].is_empty());
+    assert_eq!(TxtRecord::from_str(&txt.to_string()).unwrap(), txt);
+    assert!(TxtRecord::from_str("\"unterminated").is_err());
+    assert!(CharacterString::from_str("\\25").is_err());
+    assert!(CharacterString::new(vec![b'x'; 256]).is_err());
+
+    let long = vec![b'k'; 600];
+    let txt = TxtRecord::from_joined(&long[..]).unwrap();
+    assert_eq!(txt.strings.iter().map(|s| s.len()).collect::<Vec<_>>(), vec![255, 255, 90]);
+    assert_eq!(txt.joined(), long);
+    let rr = ResourceRecord::new::<TXT>("example.com.".parse().unwrap(), IN, txt);
+    let mut message = Message::new_response(1);
+    message.add_answer(rr);
+    let parsed = Message::parse(&message.serialize().unwrap()[..]).unwrap();
+    assert_eq!(parsed.get_answer(0).unwrap().get::<TXT>().unwrap().joined(), long);
+}
+
+#[test]
+fn txt_size_limit() {
+    use {TxtRecord, CharacterString, RRData};
+    use rr::UnknownRecord;
+    //255 full strings and one of 254 octets fill RDLENGTH exactly
+    let txt = TxtRecord::from_joined(&[b'a'; 255 * 255 + 254]).unwrap();
+    let rr = ResourceRecord::new::<TXT>("example.com.".parse().unwrap(), IN, txt);
+    let mut message = Message::new_response(1);
+    message.add_answer(rr);
+    let parsed = Message::parse(&message.serialize().unwrap()[..]).unwrap();
+    assert_eq!(parsed.get_answer(0).unwrap().get::<TXT>().unwrap().joined().len(), 65279);
+    assert!(matches!(TxtRecord::from_joined(&[b'a'; 255 * 256]), Err(Error::RdataTooLong)));
+    assert!(matches!(TxtRecord::from_joined(&[b'a'; 70000]), Err(Error::RdataTooLong)));
+    let strings = vec![CharacterString::new(vec![b'a'; 255]).unwrap(); 300];
+    assert!(matches!(TxtRecord::new(strings.clone()), Err(Error::RdataTooLong)));
+    //records built around the constructors are caught when written
+    let mut data = Vec::new();
+    assert!(matches!(RRData::TXT(TxtRecord { strings }).serialize(&mut data),
+        Err(Error::RdataTooLong)));
+    let unknown = RRData::Unknown(UnknownRecord { typecode: 999, data: vec![0; 0x10000] });
+    assert!(matches!(unknown.serialize(&mut Vec::new()), Err(Error::RdataTooLong)));
+}
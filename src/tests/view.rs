@@ -0,0 +1,74 @@
+use {MessageRef, Message, Type, Error, ResponseCode};
+use types::*;
+use prelude::*;
+
+const MX_RESPONSE: &[u8] = b"\xe3\xe8\x81\x80\x00\x01\x00\x02\x00\x00\x00\x01\
+    \x05gmail\x03com\x00\x00\x0f\x00\x01\
+    \xc0\x0c\x00\x0f\x00\x01\x00\x00\x04|\x00\x1b\x00\x05\rgmail-smtp-in\x01l\x06google\xc0\x12\
+    \xc0\x0c\x00\x0f\x00\x01\x00\x00\x04|\x00\t\x00\n\x04alt1\xc0)\
+    \x00\x00\x29\x10\x00\x01\x00\x00\x00\x00\x00";
+
+#[test]
+fn view_header_and_sections() {
+    let view = MessageRef::parse(MX_RESPONSE).unwrap();
+    assert_eq!(view.id(), 0xe3e8);
+    assert!(view.is_response());
+    assert!(view.recursion_available());
+    assert_eq!(view.num_questions(), 1);
+    assert_eq!(view.num_answers(), 2);
+    assert_eq!(view.num_additional(), 1);
+    let q = view.questions().next().unwrap();
+    assert_eq!(q.qtype(), Type::MX);
+    assert_eq!(q.class(), IN);
+    let labels : Vec<&[u8]> = q.name().labels().map(|l| l.unwrap()).collect();
+    assert_eq!(labels, vec![&b"gmail"[..], b"com"]);
+    let answers : Vec<_> = view.answers().collect();
+    assert_eq!(answers.len(), 2);
+    assert_eq!(answers[1].name().to_string(), "gmail.com.");
+    assert_eq!(answers[1].ttl(), 1148);
+    assert_eq!(answers[1].rdata(), b"\x00\n\x04alt1\xc0)");
+    let rr = answers[1].to_record().unwrap();
+    assert_eq!(rr.get::<MX>().unwrap().exchange.to_string(), "alt1.gmail-smtp-in.l.google.com.");
+    assert_eq!(view.authority().count(), 0);
+    //the OPT record carries the high bits of the response code
+    assert_eq!(view.opt().unwrap().class(), ::Class::from(4096));
+    assert_eq!(view.response_code(), ResponseCode::BadVers);
+}
+
+#[test]
+fn view_to_message() {
+    let view = MessageRef::parse(MX_RESPONSE).unwrap();
+    let message = view.to_message().unwrap();
+    assert_eq!(message.num_answers(), 2);
+    assert_eq!(message.response_code(), view.response_code());
+    assert_eq!(&Message::parse(view.as_bytes()).unwrap().serialize().unwrap()[..],
+        &message.serialize().unwrap()[..]);
+}
+
+#[test]
+fn view_framing_errors() {
+    assert!(MessageRef::parse(&MX_RESPONSE[..11]).is_err());
+    for len in 12..MX_RESPONSE.len() {
+        assert!(MessageRef::parse(&MX_RESPONSE[..len]).is_err());
+    }
+    let mut data = MX_RESPONSE.to_vec();
+    data.push(0);
+    match MessageRef::parse(&data[..]) {
+        Err(Error::Parse(p)) => match *p.error { Error::TrailingData(1) => (), _ => panic!() },
+        _ => panic!()
+    }
+}
+
+#[test]
+fn view_bad_names_fail_lazily() {
+    //framing is fine, but the question name points at itself
+    let data = b"\x00\x01\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\xc0\x0c\x00\x01\x00\x01";
+    let view = MessageRef::parse(data).unwrap();
+    let q = view.questions().next().unwrap();
+    assert_eq!(q.qtype(), Type::A);
+    let mut labels = q.name().labels();
+    match labels.next() { Some(Err(Error::InvalidPointer(12))) => (), _ => panic!() }
+    assert!(labels.next().is_none());
+    assert!(q.to_question().is_err());
+    assert!(view.to_message().is_err());
+}
@@ -0,0 +1,92 @@
+use Message;
+use ResourceRecord;
+use Question;
+use Error;
+use {WireReader, WireWriter, SliceWriter};
+use types::*;
+
+fn response() -> Message {
+    let mut message = Message::new_response(7);
+    message.add_question(Question::new_str::<A>("www.example.com", IN).unwrap());
+    for i in 0..4 {
+        message.add_answer(ResourceRecord::new_str::<A>("www.example.com", IN,
+            &format!("192.0.2.{}", i)).unwrap());
+    }
+    message
+}
+
+#[test]
+fn reader_fields() {
+    let mut r = WireReader::new(b"\x01\x02\x03\x04\x05\x06\x07");
+    assert_eq!(r.read_u8().unwrap(), 1);
+    assert_eq!(r.read_u16().unwrap(), 0x0203);
+    assert_eq!(r.read_u32().unwrap(), 0x04050607);
+    assert_eq!(r.remaining(), 0);
+    assert!(matches!(r.read_u8(), Err(Error::UnexpectedEnd)));
+    r.set_position(1);
+    r.with_limit(3, |r| {
+        assert_eq!(r.read_bytes(2).unwrap(), b"\x02\x03");
+        assert!(r.read_u8().is_err());
+    });
+    assert_eq!(r.read_u8().unwrap(), 4);
+}
+
+#[test]
+fn slice_writer_matches_vec() {
+    let message = response();
+    let full = message.serialize().unwrap();
+    let mut buf = [0u8; 512];
+    let len = {
+        let mut w = SliceWriter::new(&mut buf);
+        message.serialize_into(&mut w).unwrap();
+        w.written().len()
+    };
+    assert_eq!(&buf[..len], &full[..]);
+}
+
+#[test]
+fn slice_writer_full() {
+    let message = response();
+    let full = message.serialize().unwrap();
+    let mut buf = vec![0u8; full.len() - 1];
+    let mut w = SliceWriter::new(&mut buf);
+    assert!(matches!(message.serialize_into(&mut w), Err(Error::BufferFull)));
+}
+
+#[test]
+fn slice_writer_limits_size() {
+    //the buffer only has room for the question and two answers
+    let message = response();
+    let mut buf = [0u8; 12 + 21 + 2 * 16 + 8];
+    let data = {
+        let mut w = SliceWriter::new(&mut buf);
+        message.serialize_with_limit_into(512, &mut w).unwrap();
+        w.into_written().to_vec()
+    };
+    let parsed = Message::parse(&data[..]).unwrap();
+    assert!(parsed.is_truncated());
+    assert_eq!(parsed.num_questions(), 1);
+    assert_eq!(parsed.num_answers(), 0);
+}
+
+#[test]
+fn compression_after_prefix() {
+    //a TCP length prefix must not throw off the compression pointers
+    let message = response();
+    let full = message.serialize().unwrap();
+    let mut buf = vec![0u8, 0u8];
+    message.serialize_into(&mut buf).unwrap();
+    let len = buf.len() as u16 - 2;
+    buf.patch_u16(0, len).unwrap();
+    assert_eq!(&buf[2..], &full[..]);
+    assert_eq!(buf[1] as usize, full.len());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn bytes_mut_writer() {
+    let message = response();
+    let mut buf = ::bytes::BytesMut::with_capacity(512);
+    message.serialize_with_limit_into(512, &mut buf).unwrap();
+    assert_eq!(&buf[..], &message.serialize().unwrap()[..]);
+}
@@ -0,0 +1,182 @@
+use Name;
+use ResourceRecord;
+use ZoneParser;
+use ZoneWriter;
+use zone::ZoneErrorKind;
+use types::*;
+
+use std::fs;
+use std::env;
+use std::str::FromStr;
+use std::net::Ipv4Addr;
+
+fn parse(text: &str) -> Vec<ResourceRecord> {
+    ZoneParser::new(text, Name::from_str("example.com").unwrap())
+        .collect::<Result<Vec<_>, _>>().unwrap()
+}
+
+#[test]
+fn zone_basic_records() {
+    let records = parse("\
+$TTL 1h
+@       IN  SOA ns1 hostmaster.example.com. (
+                2024010101 ; serial
+                3h         ; refresh
+                15m 1w 300 )
+        IN  NS  ns1
+        IN  MX  10 mail.example.net.
+ns1     600 IN A 192.0.2.1
+www     CNAME ns1
+_sip._tcp SRV 0 5 5060 ns1
+");
+    assert_eq!(records.len(), 6);
+    let soa = records[0].get::<SOA>().unwrap();
+    assert_eq!(&records[0].name().to_string()[..], "example.com.");
+    assert_eq!(records[0].ttl, 3600);
+    assert_eq!(&soa.primary_ns.to_string()[..], "ns1.example.com.");
+    assert_eq!(&soa.mailbox.to_string()[..], "hostmaster.example.com.");
+    assert_eq!(soa.serial, 2024010101);
+    assert_eq!(soa.refresh, 10800);
+    assert_eq!(soa.retry, 900);
+    assert_eq!(soa.expire, 604800);
+    assert_eq!(soa.min_ttl, 300);
+    //owner is inherited from the previous record
+    assert_eq!(&records[1].name().to_string()[..], "example.com.");
+    assert_eq!(&records[1].get::<NS>().unwrap().to_string()[..], "ns1.example.com.");
+    assert_eq!(&records[2].get::<MX>().unwrap().exchange.to_string()[..], "mail.example.net.");
+    assert_eq!(&records[3].name().to_string()[..], "ns1.example.com.");
+    assert_eq!(records[3].ttl, 600);
+    assert_eq!(*records[3].get::<A>().unwrap(), Ipv4Addr::new(192, 0, 2, 1));
+    //class is inherited, TTL falls back to $TTL
+    assert_eq!(records[4].class(), IN);
+    assert_eq!(records[4].ttl, 3600);
+    let srv = records[5].get::<SRV>().unwrap();
+    assert_eq!(&records[5].name().to_string()[..], "_sip._tcp.example.com.");
+    assert_eq!(srv.port, 5060);
+}
+
+#[test]
+fn zone_origin_and_ttl_inheritance() {
+    let records = parse("\
+a 300 A 192.0.2.1
+b A 192.0.2.2
+$ORIGIN sub
+c CH A 192.0.2.3
+d A 192.0.2.4
+$ORIGIN example.org.
+@ IN A 192.0.2.5
+");
+    //without $TTL, the last explicit TTL is used
+    assert_eq!(records[1].ttl, 300);
+    assert_eq!(&records[2].name().to_string()[..], "c.sub.example.com.");
+    assert_eq!(records[2].class(), CH);
+    assert_eq!(records[3].class(), CH);
+    assert_eq!(&records[4].name().to_string()[..], "example.org.");
+    assert_eq!(records[4].class(), IN);
+}
+
+#[test]
+fn zone_txt_and_generic_rdata() {
+    let records = parse("\
+txt 60 TXT \"hello world\" unquoted \"semi;colon \\\"q\\\" \\065\"
+gen 60 TYPE65280 \\# 3 abcd ef
+a2  60 A \\# 4 C0000201
+");
+    let txt = records[0].get::<TXT>().unwrap();
+    assert_eq!(txt.strings.len(), 3);
+    assert_eq!(txt.strings[0].as_bytes(), b"hello world");
+    assert_eq!(txt.strings[1].as_bytes(), b"unquoted");
+    assert_eq!(txt.strings[2].as_bytes(), b"semi;colon \"q\" A");
+    assert_eq!(&records[1].get::<Unknown>().unwrap().data[..], b"\xab\xcd\xef");
+    assert_eq!(*records[2].get::<A>().unwrap(), Ipv4Addr::new(192, 0, 2, 1));
+}
+
+#[test]
+fn zone_include() {
+    let dir = env::temp_dir().join(format!("dnis-zone-test-{}", ::std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("hosts.inc"), "host1 A 192.0.2.10\n").unwrap();
+    fs::write(dir.join("main.zone"), "\
+$TTL 60
+$INCLUDE hosts.inc sub.example.com.
+host2 A 192.0.2.11
+").unwrap();
+    let records = ZoneParser::from_file(dir.join("main.zone"), Name::from_str("example.com").unwrap())
+        .unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(&records[0].name().to_string()[..], "host1.sub.example.com.");
+    //the include's origin doesn't leak into the parent
+    assert_eq!(&records[1].name().to_string()[..], "host2.example.com.");
+}
+
+#[test]
+fn zone_errors_have_line_numbers() {
+    let mut parser = ZoneParser::with_file_name("\
+$TTL 60
+a A 192.0.2.1
+
+b A 192.0.2.256
+c A 192.0.2.3
+", "test.zone", Name::from_str("example.com").unwrap());
+    assert!(parser.next().unwrap().is_ok());
+    let err = parser.next().unwrap().err().unwrap();
+    assert_eq!(&err.file[..], "test.zone");
+    assert_eq!(err.line, 4);
+    assert_eq!(&err.to_string()[..9], "test.zone");
+    assert!(parser.next().is_none());
+
+    let mut parser = ZoneParser::new("a A 192.0.2.1\n", Name::from_str("example.com").unwrap());
+    match parser.next().unwrap().err().unwrap().kind {
+        ZoneErrorKind::NoTtl => (),
+        e => panic!("unexpected error {}", e)
+    }
+    let mut parser = ZoneParser::new("$TTL 1\na A (\n192.0.2.1\n", Name::from_str("example.com").unwrap());
+    assert_eq!(parser.next().unwrap().err().unwrap().line, 2);
+}
+
+#[test]
+fn zone_writer_roundtrip() {
+    let text = "\
+@       3600 IN SOA ns1 hostmaster ( 1 3600 900 604800 300 )
+@       3600 IN NS  ns1.example.net.
+mail    300  IN MX  10 mail
+ns1     300  IN A   192.0.2.1
+ns1     300  IN AAAA 2001:db8::1
+_sip._tcp 60 IN SRV 0 5 5060 sip
+txt     60   IN TXT \"quote\\\" backslash\\\\ \\007bell\" \"\"
+unk     60   CH TYPE65280 \\# 3 abcdef
+empty   60   IN TYPE65281 \\# 0
+";
+    let records = parse(text);
+    let mut writer = ZoneWriter::new(Vec::new());
+    writer.set_origin(Name::from_str("example.com").unwrap()).unwrap();
+    for rr in records.iter() {
+        writer.write_record(rr).unwrap();
+    }
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(&output[..], "\
+$ORIGIN example.com.
+@ 3600 IN SOA ns1 hostmaster 1 3600 900 604800 300
+@ 3600 IN NS ns1.example.net.
+mail 300 IN MX 10 mail
+ns1 300 IN A 192.0.2.1
+ns1 300 IN AAAA 2001:db8::1
+_sip._tcp 60 IN SRV 0 5 5060 sip
+txt 60 IN TXT \"quote\\\" backslash\\\\ \\007bell\" \"\"
+unk 60 CH TYPE65280 \\# 3 abcdef
+empty 60 IN TYPE65281 \\# 0
+");
+    let reparsed = ZoneParser::new(&output, Name::root())
+        .collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(reparsed.len(), records.len());
+    for (a, b) in records.iter().zip(reparsed.iter()) {
+        assert_eq!(a.to_string(), b.to_string());
+    }
+}
+
+#[test]
+fn zone_record_display() {
+    let records = parse("www 60 IN A 192.0.2.1\n");
+    assert_eq!(&records[0].to_string()[..], "www.example.com. 60 IN A 192.0.2.1");
+}
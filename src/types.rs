@@ -1,8 +1,8 @@
-use super::{Name, Type, RRType, Error};
-use super::rr::{RRData, SrvRecord, SoaRecord, MxRecord, UnknownRecord};
+use super::{Name, Compressor, Type, RRType, Error, WireReader, WireWriter};
+use super::prelude::*;
+use super::rr::{RRData, SrvRecord, SoaRecord, MxRecord, TxtRecord, UnknownRecord};
+use super::rr::CharacterString;
 use std::net::{Ipv4Addr, Ipv6Addr};
-use std::io::{Read, Write, Cursor};
-use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
 
 pub use super::Class::{IN, CS, CH, HS};
 
@@ -27,15 +27,16 @@ impl RRType for CNAME {
     fn to_type() -> Type {
         Type::CNAME
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<Name, Error>
-        where Cursor<T>: Read
-    {
-        Name::parse(cursor)
+    fn parse(r: &mut WireReader, _: u16) -> Result<Name, Error> {
+        Name::parse(r)
+    }
+    fn serialize<W: WireWriter + ?Sized>(n: &Name, w: &mut W) -> Result<(), Error> {
+        n.serialize(w)
     }
-    fn serialize<T>(n: &Name, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
+    fn serialize_compressed<W: WireWriter + ?Sized>(n: &Name, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        n.serialize(cursor)
+        n.serialize_compressed(w, comp)
     }
 }
 
@@ -60,15 +61,16 @@ impl RRType for NS {
     fn to_type() -> Type {
         Type::NS
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<Name, Error>
-        where Cursor<T>: Read
-    {
-        Name::parse(cursor)
+    fn parse(r: &mut WireReader, _: u16) -> Result<Name, Error> {
+        Name::parse(r)
+    }
+    fn serialize<W: WireWriter + ?Sized>(n: &Name, w: &mut W) -> Result<(), Error> {
+        n.serialize(w)
     }
-    fn serialize<T>(n: &Name, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
+    fn serialize_compressed<W: WireWriter + ?Sized>(n: &Name, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        n.serialize(cursor)
+        n.serialize_compressed(w, comp)
     }
 }
 
@@ -93,15 +95,16 @@ impl RRType for PTR {
     fn to_type() -> Type {
         Type::PTR
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<Name, Error>
-        where Cursor<T>: Read
-    {
-        Name::parse(cursor)
+    fn parse(r: &mut WireReader, _: u16) -> Result<Name, Error> {
+        Name::parse(r)
+    }
+    fn serialize<W: WireWriter + ?Sized>(n: &Name, w: &mut W) -> Result<(), Error> {
+        n.serialize(w)
     }
-    fn serialize<T>(n: &Name, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
+    fn serialize_compressed<W: WireWriter + ?Sized>(n: &Name, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        n.serialize(cursor)
+        n.serialize_compressed(w, comp)
     }
 }
 
@@ -126,19 +129,15 @@ impl RRType for A {
     fn to_type() -> Type {
         Type::A
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<Ipv4Addr, Error>
-        where Cursor<T>: Read
-    {
-        let a = cursor.read_u8()?;
-        let b = cursor.read_u8()?;
-        let c = cursor.read_u8()?;
-        let d = cursor.read_u8()?;
+    fn parse(r: &mut WireReader, _: u16) -> Result<Ipv4Addr, Error> {
+        let a = r.read_u8()?;
+        let b = r.read_u8()?;
+        let c = r.read_u8()?;
+        let d = r.read_u8()?;
         Ok(Ipv4Addr::new(a, b, c, d))
     }
-    fn serialize<T>(addr: &Ipv4Addr, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
-    {
-        cursor.write_all(&addr.octets()[..])?;
+    fn serialize<W: WireWriter + ?Sized>(addr: &Ipv4Addr, w: &mut W) -> Result<(), Error> {
+        w.write_bytes(&addr.octets()[..])?;
         Ok(())
     }
 }
@@ -164,24 +163,20 @@ impl RRType for AAAA {
     fn to_type() -> Type {
         Type::AAAA
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<Ipv6Addr, Error>
-        where Cursor<T>: Read
-    {
-        let a = cursor.read_u16::<BigEndian>()?;
-        let b = cursor.read_u16::<BigEndian>()?;
-        let c = cursor.read_u16::<BigEndian>()?;
-        let d = cursor.read_u16::<BigEndian>()?;
-        let e = cursor.read_u16::<BigEndian>()?;
-        let f = cursor.read_u16::<BigEndian>()?;
-        let g = cursor.read_u16::<BigEndian>()?;
-        let h = cursor.read_u16::<BigEndian>()?;
+    fn parse(r: &mut WireReader, _: u16) -> Result<Ipv6Addr, Error> {
+        let a = r.read_u16()?;
+        let b = r.read_u16()?;
+        let c = r.read_u16()?;
+        let d = r.read_u16()?;
+        let e = r.read_u16()?;
+        let f = r.read_u16()?;
+        let g = r.read_u16()?;
+        let h = r.read_u16()?;
         Ok(Ipv6Addr::new(a, b, c, d, e, f, g, h))
     }
-    fn serialize<T>(addr: &Ipv6Addr, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
-    {
+    fn serialize<W: WireWriter + ?Sized>(addr: &Ipv6Addr, w: &mut W) -> Result<(), Error> {
         for seg in &addr.segments() {
-            cursor.write_u16::<BigEndian>(*seg)?;
+            w.write_u16(*seg)?;
         }
         Ok(())
     }
@@ -208,13 +203,11 @@ impl RRType for SRV {
     fn to_type() -> Type {
         Type::SRV
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<SrvRecord, Error>
-        where Cursor<T> : Read
-    {
-        let priority = cursor.read_u16::<BigEndian>()?;
-        let weight = cursor.read_u16::<BigEndian>()?;
-        let port = cursor.read_u16::<BigEndian>()?;
-        let target = Name::parse(cursor)?;
+    fn parse(r: &mut WireReader, _: u16) -> Result<SrvRecord, Error> {
+        let priority = r.read_u16()?;
+        let weight = r.read_u16()?;
+        let port = r.read_u16()?;
+        let target = Name::parse(r)?;
         Ok(SrvRecord{
             priority: priority,
             weight: weight,
@@ -222,13 +215,22 @@ impl RRType for SRV {
             target: target
         })
     }
-    fn serialize<T>(srv: &SrvRecord, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
+    fn serialize<W: WireWriter + ?Sized>(srv: &SrvRecord, w: &mut W) -> Result<(), Error> {
+        w.write_u16(srv.priority)?;
+        w.write_u16(srv.weight)?;
+        w.write_u16(srv.port)?;
+        srv.target.serialize(w)?;
+        Ok(())
+    }
+    fn serialize_compressed<W: WireWriter + ?Sized>(srv: &SrvRecord, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        cursor.write_u16::<BigEndian>(srv.priority)?;
-        cursor.write_u16::<BigEndian>(srv.weight)?;
-        cursor.write_u16::<BigEndian>(srv.port)?;
-        srv.target.serialize(cursor)?;
+        w.write_u16(srv.priority)?;
+        w.write_u16(srv.weight)?;
+        w.write_u16(srv.port)?;
+        //RFC 2782 forbids compressing the target, but later names may
+        //still point into it
+        srv.target.serialize_recorded(w, comp)?;
         Ok(())
     }
 }
@@ -254,16 +256,14 @@ impl RRType for SOA {
     fn to_type() -> Type {
         Type::SOA
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<SoaRecord, Error>
-        where Cursor<T>: Read
-    {
-        let pri = Name::parse(cursor)?;
-        let mail = Name::parse(cursor)?;
-        let serial = cursor.read_u32::<BigEndian>()?;
-        let refresh = cursor.read_u32::<BigEndian>()?;
-        let retry = cursor.read_u32::<BigEndian>()?;
-        let expire = cursor.read_u32::<BigEndian>()?;
-        let min_ttl = cursor.read_u32::<BigEndian>()?;
+    fn parse(r: &mut WireReader, _: u16) -> Result<SoaRecord, Error> {
+        let pri = Name::parse(r)?;
+        let mail = Name::parse(r)?;
+        let serial = r.read_u32()?;
+        let refresh = r.read_u32()?;
+        let retry = r.read_u32()?;
+        let expire = r.read_u32()?;
+        let min_ttl = r.read_u32()?;
         Ok(SoaRecord {
             primary_ns: pri,
             mailbox: mail,
@@ -274,16 +274,26 @@ impl RRType for SOA {
             min_ttl: min_ttl
         })
     }
-    fn serialize<T>(soa: &SoaRecord, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
+    fn serialize<W: WireWriter + ?Sized>(soa: &SoaRecord, w: &mut W) -> Result<(), Error> {
+        soa.primary_ns.serialize(w)?;
+        soa.mailbox.serialize(w)?;
+        w.write_u32(soa.serial)?;
+        w.write_u32(soa.refresh)?;
+        w.write_u32(soa.retry)?;
+        w.write_u32(soa.expire)?;
+        w.write_u32(soa.min_ttl)?;
+        Ok(())
+    }
+    fn serialize_compressed<W: WireWriter + ?Sized>(soa: &SoaRecord, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        soa.primary_ns.serialize(cursor)?;
-        soa.mailbox.serialize(cursor)?;
-        cursor.write_u32::<BigEndian>(soa.serial)?;
-        cursor.write_u32::<BigEndian>(soa.refresh)?;
-        cursor.write_u32::<BigEndian>(soa.retry)?;
-        cursor.write_u32::<BigEndian>(soa.expire)?;
-        cursor.write_u32::<BigEndian>(soa.min_ttl)?;
+        soa.primary_ns.serialize_compressed(w, comp)?;
+        soa.mailbox.serialize_compressed(w, comp)?;
+        w.write_u32(soa.serial)?;
+        w.write_u32(soa.refresh)?;
+        w.write_u32(soa.retry)?;
+        w.write_u32(soa.expire)?;
+        w.write_u32(soa.min_ttl)?;
         Ok(())
     }
 }
@@ -309,56 +319,71 @@ impl RRType for MX {
     fn to_type() -> Type {
         Type::MX
     }
-    fn parse<T>(cursor: &mut Cursor<T>, _: u16) -> Result<MxRecord, Error>
-        where Cursor<T>: Read
-    {
-        let pref = cursor.read_u16::<BigEndian>()?;
+    fn parse(r: &mut WireReader, _: u16) -> Result<MxRecord, Error> {
+        let pref = r.read_u16()?;
         Ok(MxRecord {
             preference: pref,
-            exchange: Name::parse(cursor)?
+            exchange: Name::parse(r)?
         })
     }
-    fn serialize<T>(mx: &MxRecord, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T>: Write
+    fn serialize<W: WireWriter + ?Sized>(mx: &MxRecord, w: &mut W) -> Result<(), Error> {
+        w.write_u16(mx.preference)?;
+        mx.exchange.serialize(w)?;
+        Ok(())
+    }
+    fn serialize_compressed<W: WireWriter + ?Sized>(mx: &MxRecord, w: &mut W,
+        comp: &mut Compressor) -> Result<(), Error>
     {
-        cursor.write_u16::<BigEndian>(mx.preference)?;
-        mx.exchange.serialize(cursor)?;
+        w.write_u16(mx.preference)?;
+        mx.exchange.serialize_compressed(w, comp)?;
         Ok(())
     }
 }
 
 pub struct TXT;
 impl RRType for TXT {
-    type D = Vec<u8>;
-    fn map(rrd: &RRData) -> Option<&Vec<u8>> {
+    type D = TxtRecord;
+    fn map(rrd: &RRData) -> Option<&TxtRecord> {
         if let &RRData::TXT(ref txt) = rrd {
             return Some(txt);
         }
         None
     }
-    fn map_mut(rrd: &mut RRData) -> Option<&mut Vec<u8>> {
+    fn map_mut(rrd: &mut RRData) -> Option<&mut TxtRecord> {
         if let &mut RRData::TXT(ref mut txt) = rrd {
             return Some(txt);
         }
         None
     }
-    fn unmap(txt: Vec<u8>) -> RRData {
+    fn unmap(txt: TxtRecord) -> RRData {
         RRData::TXT(txt)
     }
     fn to_type() -> Type {
         Type::TXT
     }
-    fn parse<T>(cursor: &mut Cursor<T>, len: u16) -> Result<Vec<u8>, Error>
-        where Cursor<T> : Read
-    {
-        let mut buf = vec![0u8; len as usize];
-        cursor.read_exact(&mut buf[..])?;
-        Ok(buf)
+    fn parse(r: &mut WireReader, len: u16) -> Result<TxtRecord, Error> {
+        //one or more strings, all within RDLENGTH (RFC 1035 section 3.3.14)
+        let end = r.position() + len as usize;
+        let mut strings = Vec::new();
+        loop {
+            if r.position() >= end {
+                return Err(Error::RdataOverrun);
+            }
+            let slen = r.read_u8()? as usize;
+            if r.position() + slen > end {
+                return Err(Error::RdataOverrun);
+            }
+            strings.push(CharacterString::new(r.read_bytes(slen)?.to_vec())?);
+            if r.position() == end {
+                return TxtRecord::new(strings);
+            }
+        }
     }
-    fn serialize<T>(txt: &Vec<u8>, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T> : Write
-    {
-        cursor.write_all(txt)?;
+    fn serialize<W: WireWriter + ?Sized>(txt: &TxtRecord, w: &mut W) -> Result<(), Error> {
+        for s in &txt.strings {
+            w.write_u8(s.len() as u8)?;
+            w.write_bytes(s.as_bytes())?;
+        }
         Ok(())
     }
 }
@@ -381,24 +406,25 @@ impl RRType for Unknown {
     fn unmap(unk: UnknownRecord) -> RRData {
         RRData::Unknown(unk)
     }
+    /// There is no single type for unknown records, so this gives TYPE0,
+    /// which is reserved (RFC 6895) and never used by a real record.  The
+    /// actual type comes from `to_type_data`.
     fn to_type() -> Type {
-        panic!("Getting type of unknown type")
+        Type::Unknown(0)
+    }
+    fn matches_type(t: Type) -> bool {
+        matches!(t, Type::Unknown(_))
     }
     fn to_type_data(rrd: &RRData) -> Result<Type, Error> {
         Ok(Type::Unknown(Self::map(rrd).ok_or(Error::ParserStateError)?.typecode))
     }
-    fn parse<T>(cursor: &mut Cursor<T>, len: u16) -> Result<UnknownRecord, Error>
-        where Cursor<T> : Read
-    {
-        let mut buf = vec![0u8; len as usize];
-        cursor.read_exact(&mut buf[..])?;
+    fn parse(r: &mut WireReader, len: u16) -> Result<UnknownRecord, Error> {
+        let data = r.read_bytes(len as usize)?.to_vec();
         //typecode will get filled in by parent
-        Ok(UnknownRecord{typecode: 0, data: buf})
+        Ok(UnknownRecord{typecode: 0, data})
     }
-    fn serialize<T>(rec: &UnknownRecord, cursor: &mut Cursor<T>) -> Result<(), Error>
-        where Cursor<T> : Write
-    {
-        cursor.write_all(&rec.data[..])?;
+    fn serialize<W: WireWriter + ?Sized>(rec: &UnknownRecord, w: &mut W) -> Result<(), Error> {
+        w.write_bytes(&rec.data[..])?;
         Ok(())
     }
 }
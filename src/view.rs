@@ -0,0 +1,494 @@
+use std::fmt;
+
+use super::{Name, Question, ResourceRecord, Message, Error, Type, Class, Opcode,
+    ResponseCode};
+use super::WireReader;
+use super::parse_error::{ParseError, Section, locate};
+use super::rr::rdata_names;
+use super::prelude::*;
+
+const HEADER_LEN: usize = 12;
+const MAX_POINTER: usize = 0x3FFF;
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    (data[pos] as u16) << 8 | data[pos + 1] as u16
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    (read_u16(data, pos) as u32) << 16 | read_u16(data, pos + 2) as u32
+}
+
+pub fn truncated(offset: usize, expected: &'static str) -> Error {
+    Error::Parse(ParseError::new(offset, expected, Error::UnexpectedEnd))
+}
+
+/// Find the end of the name at `pos` without decoding it
+pub fn skip_name(data: &[u8], mut pos: usize) -> Result<usize, Error> {
+    loop {
+        let len = *data.get(pos).ok_or_else(|| truncated(pos, "name"))?;
+        match len >> 6 {
+            0b00 if len == 0 => return Ok(pos + 1),
+            0b00 => pos += 1 + len as usize,
+            0b11 if pos + 2 <= data.len() => return Ok(pos + 2),
+            0b11 => return Err(truncated(pos, "name")),
+            _ => return Err(Error::UnknownLabelFormat(pos))
+        }
+    }
+}
+
+/// Note the labels of the name at `pos` that a pointer could reach, and
+/// find the end of the name
+fn scan_name(data: &[u8], mut pos: usize, labels: &mut Vec<u16>) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+        match len >> 6 {
+            0b00 if len == 0 => return Some(pos + 1),
+            0b00 if pos + 1 + len <= data.len() => {
+                if pos <= MAX_POINTER {
+                    labels.push(pos as u16);
+                }
+                pos += 1 + len;
+            }
+            0b11 if pos + 2 <= data.len() => return Some(pos + 2),
+            _ => return None
+        }
+    }
+}
+
+/// Where the labels that compression pointers may refer to start: those of
+/// the owner names, and of the names in the rdata of types the crate knows.
+/// This takes one pass over the front of the message, as far as a pointer
+/// can reach, and stops early at anything malformed.
+pub fn label_starts(data: &[u8]) -> Vec<u16> {
+    let mut labels = Vec::new();
+    if data.len() < HEADER_LEN {
+        return labels;
+    }
+    let mut pos = HEADER_LEN;
+    for n in 0..4 {
+        for _ in 0..read_u16(data, 4 + 2 * n) {
+            if pos > MAX_POINTER {
+                return labels;
+            }
+            pos = match scan_name(data, pos, &mut labels) {
+                Some(end) => end,
+                None => return labels
+            };
+            if n == 0 {
+                pos += 4;
+                continue;
+            }
+            if pos + 10 > data.len() {
+                return labels;
+            }
+            let rdata = pos + 10;
+            let end = rdata + read_u16(data, pos + 8) as usize;
+            if end > data.len() {
+                return labels;
+            }
+            let (fixed, count) = rdata_names(Type::from(read_u16(data, pos)));
+            let mut name = rdata + fixed;
+            for _ in 0..count {
+                match scan_name(&data[..end], name, &mut labels) {
+                    Some(next) => name = next,
+                    None => break
+                }
+            }
+            pos = end;
+        }
+    }
+    labels
+}
+
+/// A message borrowed from its wire form
+///
+/// Creating the view checks that the sections are well framed and notes
+/// where labels start, so that compression pointers can be checked.  Names
+/// and rdata are only decoded when asked for, and that list of offsets is
+/// all that is allocated.
+#[derive(Clone)]
+pub struct MessageRef<'a> {
+    data: &'a [u8],
+    /// Offset of the question, answer, authority and additional sections
+    starts: [usize; 4],
+    /// From `label_starts`
+    labels: Vec<u16>
+}
+
+impl<'a> MessageRef<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN {
+            return Err(locate(truncated(0, "header"), 0, Section::Header, 0, "header"));
+        }
+        let mut starts = [HEADER_LEN; 4];
+        let mut pos = HEADER_LEN;
+        let sections = [Section::Question, Section::Answer, Section::Authority,
+            Section::Additional];
+        for (n, &section) in sections.iter().enumerate() {
+            starts[n] = pos;
+            let count = read_u16(data, 4 + 2 * n) as usize;
+            for i in 0..count {
+                let start = pos;
+                pos = Self::skip_entry(data, pos, section)
+                    .map_err(|e| locate(e, start, section, i, "record"))?;
+            }
+        }
+        if pos < data.len() {
+            let e = Error::TrailingData(data.len() - pos);
+            let count = read_u16(data, 10) as usize;
+            return Err(locate(e, pos, Section::Additional, count, "end of message"));
+        }
+        Ok(MessageRef { data, starts, labels: label_starts(data) })
+    }
+    fn skip_entry(data: &[u8], pos: usize, section: Section) -> Result<usize, Error> {
+        let pos = skip_name(data, pos)?;
+        if section == Section::Question {
+            if pos + 4 > data.len() {
+                return Err(truncated(pos, "question type"));
+            }
+            return Ok(pos + 4);
+        }
+        if pos + 10 > data.len() {
+            return Err(truncated(pos, "record type"));
+        }
+        let end = pos + 10 + read_u16(data, pos + 8) as usize;
+        if end > data.len() {
+            return Err(truncated(pos + 10, "rdata"));
+        }
+        Ok(end)
+    }
+    /// The wire data the view borrows
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+    fn flags(&self) -> u16 {
+        read_u16(self.data, 2)
+    }
+    pub fn id(&self) -> u16 {
+        read_u16(self.data, 0)
+    }
+    pub fn is_request(&self) -> bool {
+        self.flags() & (1 << 15) == 0
+    }
+    pub fn is_response(&self) -> bool {
+        !self.is_request()
+    }
+    pub fn opcode(&self) -> Opcode {
+        Opcode::from((self.flags() >> 11) & 0b1111)
+    }
+    pub fn is_authoritative(&self) -> bool {
+        self.flags() & (1 << 10) != 0
+    }
+    pub fn is_truncated(&self) -> bool {
+        self.flags() & (1 << 9) != 0
+    }
+    pub fn recursion_desired(&self) -> bool {
+        self.flags() & (1 << 8) != 0
+    }
+    pub fn recursion_available(&self) -> bool {
+        self.flags() & (1 << 7) != 0
+    }
+    pub fn authenticated_data(&self) -> bool {
+        self.flags() & (1 << 5) != 0
+    }
+    pub fn checking_disabled(&self) -> bool {
+        self.flags() & (1 << 4) != 0
+    }
+    /// The response code, including the extended bits from the OPT record
+    pub fn response_code(&self) -> ResponseCode {
+        let high = self.opt().map_or(0, |o| o.ttl() >> 24) as u16;
+        ResponseCode::from(high << 4 | (self.flags() & 0b1111))
+    }
+    pub fn num_questions(&self) -> usize {
+        read_u16(self.data, 4) as usize
+    }
+    pub fn num_answers(&self) -> usize {
+        read_u16(self.data, 6) as usize
+    }
+    pub fn num_authority(&self) -> usize {
+        read_u16(self.data, 8) as usize
+    }
+    pub fn num_additional(&self) -> usize {
+        read_u16(self.data, 10) as usize
+    }
+    pub fn questions(&self) -> QuestionRefs<'_> {
+        QuestionRefs {
+            data: self.data,
+            labels: &self.labels[..],
+            pos: self.starts[0],
+            remaining: self.num_questions()
+        }
+    }
+    fn records(&self, n: usize) -> RecordRefs<'_> {
+        RecordRefs {
+            data: self.data,
+            labels: &self.labels[..],
+            pos: self.starts[n],
+            remaining: read_u16(self.data, 4 + 2 * n) as usize
+        }
+    }
+    pub fn answers(&self) -> RecordRefs<'_> {
+        self.records(1)
+    }
+    pub fn authority(&self) -> RecordRefs<'_> {
+        self.records(2)
+    }
+    /// The additional section, including any OPT record
+    pub fn additional(&self) -> RecordRefs<'_> {
+        self.records(3)
+    }
+    /// The first OPT record in the additional section
+    pub fn opt(&self) -> Option<RecordRef<'_>> {
+        self.additional().find(|r| r.rrtype() == Type::OPT)
+    }
+    /// Decode the whole message
+    pub fn to_message(&self) -> Result<Message, Error> {
+        Message::parse(self.data)
+    }
+}
+
+/// A possibly compressed name within a message
+#[derive(Clone, Copy)]
+pub struct NameRef<'a> {
+    data: &'a [u8],
+    labels: &'a [u16],
+    offset: usize
+}
+
+impl<'a> NameRef<'a> {
+    /// Offset of the name within the message
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    /// The labels of the name, following compression pointers
+    pub fn labels(&self) -> LabelRefs<'a> {
+        LabelRefs {
+            data: self.data,
+            labels: self.labels,
+            pos: self.offset,
+            lowest: self.offset,
+            len: 1,
+            done: false
+        }
+    }
+    pub fn to_name(&self) -> Result<Name, Error> {
+        let mut r = WireReader::with_labels(self.data, self.labels);
+        r.set_position(self.offset);
+        Name::parse(&mut r)
+    }
+}
+
+impl<'a> fmt::Display for NameRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_name() {
+            Ok(n) => n.fmt(f),
+            Err(_) => write!(f, "<invalid name at {}>", self.offset)
+        }
+    }
+}
+
+/// Iterator over the labels of a `NameRef`
+///
+/// Applies the same checks as `Name::parse` and stops after the first
+/// error.
+pub struct LabelRefs<'a> {
+    data: &'a [u8],
+    labels: &'a [u16],
+    pos: usize,
+    lowest: usize,
+    len: usize,
+    done: bool
+}
+
+impl<'a> LabelRefs<'a> {
+    fn next_label(&mut self) -> Result<Option<&'a [u8]>, Error> {
+        loop {
+            let pos = self.pos;
+            let len = *self.data.get(pos).ok_or_else(|| truncated(pos, "name"))? as usize;
+            match len >> 6 {
+                0b00 if len == 0 => return Ok(None),
+                0b00 => {
+                    let label = self.data.get(pos + 1..pos + 1 + len)
+                        .ok_or_else(|| truncated(pos, "name"))?;
+                    self.len += 1 + len;
+                    if self.len > 255 {
+                        return Err(Error::NameTooLong);
+                    }
+                    self.pos += 1 + len;
+                    return Ok(Some(label));
+                }
+                0b11 => {
+                    if pos + 2 > self.data.len() {
+                        return Err(truncated(pos, "name"));
+                    }
+                    let off = (read_u16(self.data, pos) & 0x3FFF) as usize;
+                    if off >= self.lowest || off < HEADER_LEN
+                        || self.labels.binary_search(&(off as u16)).is_err()
+                    {
+                        return Err(Error::InvalidPointer(pos));
+                    }
+                    self.lowest = off;
+                    self.pos = off;
+                }
+                _ => return Err(Error::UnknownLabelFormat(pos))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for LabelRefs<'a> {
+    type Item = Result<&'a [u8], Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_label() {
+            Ok(Some(l)) => Some(Ok(l)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A question borrowed from a message
+#[derive(Clone, Copy)]
+pub struct QuestionRef<'a> {
+    name: NameRef<'a>,
+    qtype: u16,
+    qclass: u16
+}
+
+impl<'a> QuestionRef<'a> {
+    pub fn name(&self) -> NameRef<'a> {
+        self.name
+    }
+    pub fn qtype(&self) -> Type {
+        Type::from(self.qtype)
+    }
+    pub fn class(&self) -> Class {
+        Class::from(self.qclass & 0x7FFF)
+    }
+    pub fn prefer_unicast(&self) -> bool {
+        self.qclass & 0x8000 != 0
+    }
+    pub fn to_question(&self) -> Result<Question, Error> {
+        let mut r = WireReader::with_labels(self.name.data, self.name.labels);
+        r.set_position(self.name.offset);
+        Question::parse(&mut r)
+    }
+}
+
+/// Iterator over the questions of a `MessageRef`
+pub struct QuestionRefs<'a> {
+    data: &'a [u8],
+    labels: &'a [u16],
+    pos: usize,
+    remaining: usize
+}
+
+impl<'a> Iterator for QuestionRefs<'a> {
+    type Item = QuestionRef<'a>;
+    fn next(&mut self) -> Option<QuestionRef<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        //the framing was checked by MessageRef::parse
+        let name = NameRef { data: self.data, labels: self.labels, offset: self.pos };
+        let end = skip_name(self.data, self.pos).ok()?;
+        self.pos = end + 4;
+        Some(QuestionRef {
+            name,
+            qtype: read_u16(self.data, end),
+            qclass: read_u16(self.data, end + 2)
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// A resource record borrowed from a message
+#[derive(Clone, Copy)]
+pub struct RecordRef<'a> {
+    name: NameRef<'a>,
+    rrtype: u16,
+    rrclass: u16,
+    ttl: u32,
+    rdata_offset: usize,
+    rdata: &'a [u8]
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn name(&self) -> NameRef<'a> {
+        self.name
+    }
+    pub fn rrtype(&self) -> Type {
+        Type::from(self.rrtype)
+    }
+    /// The class, or the UDP payload size for OPT records
+    pub fn class(&self) -> Class {
+        Class::from(self.rrclass & 0x7FFF)
+    }
+    pub fn multicast_unique(&self) -> bool {
+        self.rrclass & 0x8000 != 0
+    }
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+    /// The undecoded rdata
+    pub fn rdata(&self) -> &'a [u8] {
+        self.rdata
+    }
+    /// Offset of the rdata within the message, for resolving compressed
+    /// names in it
+    pub fn rdata_offset(&self) -> usize {
+        self.rdata_offset
+    }
+    /// Decode the record.  OPT records can't be decoded this way.
+    pub fn to_record(&self) -> Result<ResourceRecord, Error> {
+        let mut r = WireReader::with_labels(self.name.data, self.name.labels);
+        r.set_position(self.name.offset);
+        ResourceRecord::parse(&mut r)
+    }
+}
+
+/// Iterator over the records of one section of a `MessageRef`
+pub struct RecordRefs<'a> {
+    data: &'a [u8],
+    labels: &'a [u16],
+    pos: usize,
+    remaining: usize
+}
+
+impl<'a> Iterator for RecordRefs<'a> {
+    type Item = RecordRef<'a>;
+    fn next(&mut self) -> Option<RecordRef<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        //the framing was checked by MessageRef::parse
+        let name = NameRef { data: self.data, labels: self.labels, offset: self.pos };
+        let end = skip_name(self.data, self.pos).ok()?;
+        let len = read_u16(self.data, end + 8) as usize;
+        self.pos = end + 10 + len;
+        Some(RecordRef {
+            name,
+            rrtype: read_u16(self.data, end),
+            rrclass: read_u16(self.data, end + 2),
+            ttl: read_u32(self.data, end + 4),
+            rdata_offset: end + 10,
+            rdata: &self.data[end + 10..self.pos]
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
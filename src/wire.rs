@@ -0,0 +1,241 @@
+//! Reading and writing the DNS wire format
+//!
+//! Parsing works on a `WireReader` over the whole message, since
+//! compression pointers can refer to anywhere before the current name.
+//! Serializing works on any `WireWriter`: a `Vec<u8>`, a fixed buffer
+//! through `SliceWriter`, or, with the `bytes` feature, a `BytesMut`.
+
+use super::Error;
+use super::registry::Registry;
+use super::prelude::*;
+
+use alloc::borrow::Cow;
+
+/// Compression pointers hold 14 bits, so labels past this can't be targets
+const MAX_POINTER: usize = 0x3FFF;
+
+/// Reads big-endian fields from a message, keeping the whole message
+/// visible so that compression pointers can be followed
+///
+/// The reader also keeps track of where the labels it has read start,
+/// since a compression pointer may only refer to one of those.
+#[derive(Clone, Debug)]
+pub struct WireReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Starts of the labels read so far that a pointer could reach, in
+    /// order
+    labels: Cow<'a, [u16]>,
+    /// Parsers for record types defined outside the crate
+    registry: Option<&'a Registry>
+}
+
+impl<'a> WireReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        WireReader { data, pos: 0, labels: Cow::Owned(Vec::new()), registry: None }
+    }
+    /// A reader that already knows where the labels in `data` start, such
+    /// as a list found by scanning the message beforehand
+    pub fn with_labels<L: Into<Cow<'a, [u16]>>>(data: &'a [u8], labels: L) -> Self {
+        WireReader { labels: labels.into(), ..Self::new(data) }
+    }
+    /// A reader that decodes the record types in `registry` too
+    pub fn with_registry(data: &'a [u8], registry: &'a Registry) -> Self {
+        WireReader { registry: Some(registry), ..Self::new(data) }
+    }
+    pub fn registry(&self) -> Option<&'a Registry> {
+        self.registry
+    }
+    /// The whole buffer, including what has already been read
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+    pub fn set_position(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+    /// Run `f` with the data cut off at `end`.  Used to keep a parser
+    /// within a length-delimited field.
+    pub fn with_limit<T, F>(&mut self, end: usize, f: F) -> T
+        where F: FnOnce(&mut Self) -> T
+    {
+        let data = self.data;
+        self.data = &data[..end.min(data.len())];
+        let ret = f(self);
+        self.data = data;
+        ret
+    }
+    /// Note that a label starts at `pos`
+    pub fn add_label(&mut self, pos: usize) {
+        if pos > MAX_POINTER {
+            return;
+        }
+        if let Err(i) = self.labels.binary_search(&(pos as u16)) {
+            self.labels.to_mut().insert(i, pos as u16);
+        }
+    }
+    /// Whether a label read earlier starts at `pos`
+    pub fn is_label(&self, pos: usize) -> bool {
+        pos <= MAX_POINTER && self.labels.binary_search(&(pos as u16)).is_ok()
+    }
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.remaining() {
+            return Err(Error::UnexpectedEnd);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+    pub fn read_u16(&mut self) -> Result<u16, Error> {
+        let b = self.read_bytes(2)?;
+        Ok((b[0] as u16) << 8 | b[1] as u16)
+    }
+    pub fn read_u32(&mut self) -> Result<u32, Error> {
+        let b = self.read_bytes(4)?;
+        Ok((b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32)
+    }
+}
+
+/// A buffer that messages can be serialized into
+///
+/// Positions are offsets into the whole buffer.  Writers only ever append,
+/// except that `patch` may overwrite octets already written (to fill in
+/// lengths and counts) and `truncate` may drop them again.
+pub trait WireWriter {
+    /// Number of octets in the buffer
+    fn position(&self) -> usize;
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Error>;
+    /// Overwrite octets that were already written
+    fn patch(&mut self, pos: usize, data: &[u8]) -> Result<(), Error>;
+    /// Drop everything from `pos` on
+    fn truncate(&mut self, pos: usize);
+    /// The largest position the writer can reach, if it is bounded
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+    fn write_u8(&mut self, x: u8) -> Result<(), Error> {
+        self.write_bytes(&[x])
+    }
+    fn write_u16(&mut self, x: u16) -> Result<(), Error> {
+        self.write_bytes(&[(x >> 8) as u8, x as u8])
+    }
+    fn write_u32(&mut self, x: u32) -> Result<(), Error> {
+        self.write_bytes(&[(x >> 24) as u8, (x >> 16) as u8, (x >> 8) as u8, x as u8])
+    }
+    fn patch_u16(&mut self, pos: usize, x: u16) -> Result<(), Error> {
+        self.patch(pos, &[(x >> 8) as u8, x as u8])
+    }
+}
+
+impl<W: WireWriter + ?Sized> WireWriter for &mut W {
+    fn position(&self) -> usize {
+        (**self).position()
+    }
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        (**self).write_bytes(data)
+    }
+    fn patch(&mut self, pos: usize, data: &[u8]) -> Result<(), Error> {
+        (**self).patch(pos, data)
+    }
+    fn truncate(&mut self, pos: usize) {
+        (**self).truncate(pos)
+    }
+    fn capacity(&self) -> Option<usize> {
+        (**self).capacity()
+    }
+}
+
+fn patch_slice(buf: &mut [u8], pos: usize, data: &[u8]) -> Result<(), Error> {
+    match buf.get_mut(pos..pos + data.len()) {
+        Some(dest) => {
+            dest.copy_from_slice(data);
+            Ok(())
+        }
+        None => Err(Error::ParserStateError)
+    }
+}
+
+impl WireWriter for Vec<u8> {
+    fn position(&self) -> usize {
+        self.len()
+    }
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+    fn patch(&mut self, pos: usize, data: &[u8]) -> Result<(), Error> {
+        patch_slice(&mut self[..], pos, data)
+    }
+    fn truncate(&mut self, pos: usize) {
+        Vec::truncate(self, pos);
+    }
+}
+
+/// Writes into a fixed buffer without allocating, failing with
+/// `BufferFull` when it runs out of room
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        SliceWriter { buf, pos: 0 }
+    }
+    /// The part of the buffer written so far
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+    pub fn into_written(self) -> &'a mut [u8] {
+        &mut self.buf[..self.pos]
+    }
+}
+
+impl<'a> WireWriter for SliceWriter<'a> {
+    fn position(&self) -> usize {
+        self.pos
+    }
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        let end = self.pos + data.len();
+        if end > self.buf.len() {
+            return Err(Error::BufferFull);
+        }
+        self.buf[self.pos..end].copy_from_slice(data);
+        self.pos = end;
+        Ok(())
+    }
+    fn patch(&mut self, pos: usize, data: &[u8]) -> Result<(), Error> {
+        patch_slice(&mut self.buf[..self.pos], pos, data)
+    }
+    fn truncate(&mut self, pos: usize) {
+        self.pos = self.pos.min(pos);
+    }
+    fn capacity(&self) -> Option<usize> {
+        Some(self.buf.len())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl WireWriter for ::bytes::BytesMut {
+    fn position(&self) -> usize {
+        self.len()
+    }
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+    fn patch(&mut self, pos: usize, data: &[u8]) -> Result<(), Error> {
+        patch_slice(&mut self[..], pos, data)
+    }
+    fn truncate(&mut self, pos: usize) {
+        ::bytes::BytesMut::truncate(self, pos);
+    }
+}
@@ -0,0 +1,562 @@
+//! Master file (zone file) parsing and writing, as described in RFC 1035
+//! section 5
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::error;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use super::{Name, Error, Type, Class, ResourceRecord};
+use super::{RRData, SrvRecord, SoaRecord, MxRecord, TxtRecord, CharacterString};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ZoneErrorKind {
+        IOError(err: io::Error) {
+            from()
+            description("I/O Error")
+            display("I/O Error: {}", err)
+            cause(err)
+        }
+        DNS(err: Error) {
+            from()
+            description("Invalid DNS data")
+            display("Invalid DNS data: {}", err)
+            cause(err)
+        }
+        Syntax(msg: String) {
+            description("Syntax error")
+            display("Syntax error: {}", msg)
+        }
+        NoTtl {
+            description("Record has no TTL and there is no $TTL default")
+        }
+        NoOwner {
+            description("Record has no owner and there is no previous owner")
+        }
+        IncludeDepth {
+            description("$INCLUDE nested too deeply")
+        }
+    }
+}
+
+/// An error in a master file, with the file and line it occurred on
+#[derive(Debug)]
+pub struct ZoneError {
+    pub file: String,
+    pub line: usize,
+    pub kind: ZoneErrorKind
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.kind)
+    }
+}
+
+impl error::Error for ZoneError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
+fn syntax<T>(msg: &str) -> Result<T, ZoneErrorKind> {
+    Err(ZoneErrorKind::Syntax(msg.to_string()))
+}
+
+struct Token {
+    text: String,
+    quoted: bool
+}
+
+/// One logical line of the file, after joining parenthesised lines
+struct Entry {
+    line: usize,
+    leading_space: bool,
+    tokens: Vec<Token>
+}
+
+/// Split a master file into entries, up to the first syntax error
+fn tokenize(text: &str) -> (VecDeque<Entry>, Option<(usize, ZoneErrorKind)>) {
+    let mut entries = VecDeque::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut entry = Entry { line: 1, leading_space: false, tokens: Vec::new() };
+    let mut token : Option<Token> = None;
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        if line_start && depth == 0 {
+            entry.line = line;
+            entry.leading_space = c == ' ' || c == '\t';
+        }
+        line_start = false;
+        match c {
+            '\\' => {
+                let t = token.get_or_insert(Token { text: String::new(), quoted: false });
+                t.text.push(c);
+                match chars.next() {
+                    Some(e) => {
+                        if e == '\n' { line += 1; }
+                        t.text.push(e);
+                    }
+                    None => return (entries, Some((line, ZoneErrorKind::Syntax(
+                        "escape at end of file".to_string()))))
+                }
+            }
+            '"' => {
+                if let Some(t) = token.take() { entry.tokens.push(t); }
+                let mut t = Token { text: String::new(), quoted: true };
+                let start = line;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            t.text.push('\\');
+                            if let Some(e) = chars.next() {
+                                if e == '\n' { line += 1; }
+                                t.text.push(e);
+                            }
+                        }
+                        Some(x) => {
+                            if x == '\n' { line += 1; }
+                            t.text.push(x);
+                        }
+                        None => return (entries, Some((start, ZoneErrorKind::Syntax(
+                            "unterminated quoted string".to_string()))))
+                    }
+                }
+                entry.tokens.push(t);
+            }
+            ';' => {
+                while let Some(&x) = chars.peek() {
+                    if x == '\n' { break; }
+                    chars.next();
+                }
+            }
+            '(' | ')' | ' ' | '\t' | '\r' | '\n' => {
+                if let Some(t) = token.take() { entry.tokens.push(t); }
+                if c == '(' { depth += 1; }
+                if c == ')' {
+                    if depth == 0 {
+                        return (entries, Some((line, ZoneErrorKind::Syntax(
+                            "unbalanced parentheses".to_string()))));
+                    }
+                    depth -= 1;
+                }
+                if c == '\n' {
+                    line += 1;
+                    if depth == 0 {
+                        line_start = true;
+                        if !entry.tokens.is_empty() {
+                            entries.push_back(entry);
+                        }
+                        entry = Entry { line, leading_space: false, tokens: Vec::new() };
+                    }
+                }
+            }
+            x => {
+                token.get_or_insert(Token { text: String::new(), quoted: false }).text.push(x);
+            }
+        }
+    }
+    if depth != 0 {
+        return (entries, Some((entry.line, ZoneErrorKind::Syntax(
+            "unbalanced parentheses".to_string()))));
+    }
+    if let Some(t) = token.take() { entry.tokens.push(t); }
+    if !entry.tokens.is_empty() {
+        entries.push_back(entry);
+    }
+    (entries, None)
+}
+
+/// Parse a TTL, either as plain seconds or with BIND-style units (`1h30m`)
+fn parse_ttl(s: &str) -> Option<u32> {
+    if let Ok(x) = s.parse::<u32>() {
+        return Some(x);
+    }
+    let mut total = 0u32;
+    let mut num : Option<u32> = None;
+    for c in s.chars() {
+        if let Some(d) = c.to_digit(10) {
+            num = Some(num.unwrap_or(0).checked_mul(10)?.checked_add(d)?);
+            continue;
+        }
+        let mult = match c.to_ascii_lowercase() {
+            'w' => 604800,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None
+        };
+        total = total.checked_add(num.take()?.checked_mul(mult)?)?;
+    }
+    if num.is_some() {
+        return None;
+    }
+    Some(total)
+}
+
+fn parse_name(s: &str, origin: &Name) -> Result<Name, ZoneErrorKind> {
+    if s == "@" {
+        return Ok(origin.clone());
+    }
+    Ok(Name::from_str(s)?.qualify(origin)?)
+}
+
+fn parse_num<T: FromStr>(s: &str) -> Result<T, ZoneErrorKind> {
+    s.parse::<T>().map_err(|_| ZoneErrorKind::Syntax(format!("invalid number {}", s)))
+}
+
+/// Parse the RFC 3597 `\# length hex` form
+fn parse_generic(t: Type, tokens: &[Token]) -> Result<RRData, ZoneErrorKind> {
+    if tokens.is_empty() {
+        return syntax("missing rdata length");
+    }
+    let len = parse_num::<u16>(&tokens[0].text)? as usize;
+    let hex : String = tokens[1..].iter().map(|t| &t.text[..]).collect();
+    if hex.len() != len * 2 {
+        return syntax("rdata length does not match data");
+    }
+    let mut data = Vec::with_capacity(len);
+    for i in 0..len {
+        match u8::from_str_radix(hex.get(i * 2..i * 2 + 2).unwrap_or(""), 16) {
+            Ok(x) => data.push(x),
+            Err(_) => return syntax("invalid hex in rdata")
+        }
+    }
+    Ok(RRData::from_wire(t, &data[..])?)
+}
+
+fn parse_rdata(t: Type, tokens: &[Token], origin: &Name) -> Result<RRData, ZoneErrorKind> {
+    if !tokens.is_empty() && !tokens[0].quoted && tokens[0].text == "\\#" {
+        return parse_generic(t, &tokens[1..]);
+    }
+    let expect = |n: usize| {
+        if tokens.len() != n {
+            return syntax(&format!("{} expects {} rdata fields, found {}", t, n, tokens.len()));
+        }
+        Ok(())
+    };
+    Ok(match t {
+        Type::A => {
+            expect(1)?;
+            RRData::A(Ipv4Addr::from_str(&tokens[0].text)
+                .or_else(|_| syntax("invalid IPv4 address"))?)
+        }
+        Type::AAAA => {
+            expect(1)?;
+            RRData::AAAA(Ipv6Addr::from_str(&tokens[0].text)
+                .or_else(|_| syntax("invalid IPv6 address"))?)
+        }
+        Type::NS => { expect(1)?; RRData::NS(parse_name(&tokens[0].text, origin)?) }
+        Type::CNAME => { expect(1)?; RRData::CNAME(parse_name(&tokens[0].text, origin)?) }
+        Type::PTR => { expect(1)?; RRData::PTR(parse_name(&tokens[0].text, origin)?) }
+        Type::MX => {
+            expect(2)?;
+            RRData::MX(MxRecord {
+                preference: parse_num(&tokens[0].text)?,
+                exchange: parse_name(&tokens[1].text, origin)?
+            })
+        }
+        Type::SRV => {
+            expect(4)?;
+            RRData::SRV(SrvRecord {
+                priority: parse_num(&tokens[0].text)?,
+                weight: parse_num(&tokens[1].text)?,
+                port: parse_num(&tokens[2].text)?,
+                target: parse_name(&tokens[3].text, origin)?
+            })
+        }
+        Type::SOA => {
+            expect(7)?;
+            let time = |s: &str| parse_ttl(s)
+                .ok_or_else(|| ZoneErrorKind::Syntax(format!("invalid time {}", s)));
+            RRData::SOA(SoaRecord {
+                primary_ns: parse_name(&tokens[0].text, origin)?,
+                mailbox: parse_name(&tokens[1].text, origin)?,
+                serial: parse_num(&tokens[2].text)?,
+                refresh: time(&tokens[3].text)?,
+                retry: time(&tokens[4].text)?,
+                expire: time(&tokens[5].text)?,
+                min_ttl: time(&tokens[6].text)?
+            })
+        }
+        Type::TXT => {
+            if tokens.is_empty() {
+                return syntax("TXT needs at least one string");
+            }
+            let mut strings = Vec::new();
+            for tok in tokens {
+                strings.push(CharacterString::from_str(&tok.text)?);
+            }
+            RRData::TXT(TxtRecord::new(strings)?)
+        }
+        _ => return syntax(&format!("{} rdata must use the \\# generic form", t))
+    })
+}
+
+struct Source {
+    file: String,
+    dir: Option<PathBuf>,
+    origin: Name,
+    owner: Option<Name>,
+    entries: VecDeque<Entry>,
+    error: Option<(usize, ZoneErrorKind)>
+}
+
+impl Source {
+    fn new(text: &str, file: String, dir: Option<PathBuf>, origin: Name,
+        owner: Option<Name>) -> Self
+    {
+        let (entries, error) = tokenize(text);
+        //the origin of a master file is always absolute, and qualifying
+        //with the root can't make it any longer
+        let origin = origin.qualify(&Name::root()).unwrap_or(origin);
+        Source {
+            file,
+            dir,
+            origin,
+            owner,
+            entries,
+            error
+        }
+    }
+}
+
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Iterates over the records in a master file
+///
+/// Understands `$ORIGIN`, `$TTL` and `$INCLUDE`, relative names and `@`,
+/// owner, TTL and class inheritance, parentheses, comments and quoted
+/// strings.  Rdata is parsed for the types this crate knows about; any
+/// type may be given in the RFC 3597 `\# length hex` form.  Iteration
+/// stops after the first error.
+pub struct ZoneParser {
+    sources: Vec<Source>,
+    default_ttl: Option<u32>,
+    last_ttl: Option<u32>,
+    last_class: Class
+}
+
+impl ZoneParser {
+    /// Parse zone data held in memory
+    pub fn new(text: &str, origin: Name) -> Self {
+        Self::with_file_name(text, "<input>", origin)
+    }
+    /// Parse zone data held in memory, using `file` in error messages
+    pub fn with_file_name(text: &str, file: &str, origin: Name) -> Self {
+        ZoneParser {
+            sources: vec![Source::new(text, file.to_string(), None, origin, None)],
+            default_ttl: None,
+            last_ttl: None,
+            last_class: Class::IN
+        }
+    }
+    /// Parse a zone file.  `$INCLUDE` paths are relative to its directory.
+    pub fn from_file<P: AsRef<Path>>(path: P, origin: Name) -> Result<Self, ZoneError> {
+        let path = path.as_ref();
+        let file = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| ZoneError {
+            file: file.clone(),
+            line: 0,
+            kind: ZoneErrorKind::from(e)
+        })?;
+        let dir = path.parent().map(|p| p.to_path_buf());
+        Ok(ZoneParser {
+            sources: vec![Source::new(&text, file, dir, origin, None)],
+            default_ttl: None,
+            last_ttl: None,
+            last_class: Class::IN
+        })
+    }
+    fn directive(&mut self, entry: &Entry) -> Result<(), ZoneErrorKind> {
+        let args = &entry.tokens[1..];
+        match &entry.tokens[0].text.to_ascii_uppercase()[..] {
+            "$ORIGIN" => {
+                if args.len() != 1 {
+                    return syntax("$ORIGIN expects one name");
+                }
+                let src = self.sources.last_mut().unwrap();
+                src.origin = parse_name(&args[0].text, &src.origin)?;
+            }
+            "$TTL" => {
+                if args.len() != 1 {
+                    return syntax("$TTL expects one TTL");
+                }
+                self.default_ttl = Some(parse_ttl(&args[0].text)
+                    .ok_or_else(|| ZoneErrorKind::Syntax("invalid TTL".to_string()))?);
+            }
+            "$INCLUDE" => {
+                if args.is_empty() || args.len() > 2 {
+                    return syntax("$INCLUDE expects a file name and optional origin");
+                }
+                if self.sources.len() >= MAX_INCLUDE_DEPTH {
+                    return Err(ZoneErrorKind::IncludeDepth);
+                }
+                let (path, origin, owner) = {
+                    let src = self.sources.last().unwrap();
+                    let mut path = PathBuf::from(&args[0].text);
+                    if let Some(ref dir) = src.dir {
+                        path = dir.join(path);
+                    }
+                    let origin = match args.get(1) {
+                        Some(o) => parse_name(&o.text, &src.origin)?,
+                        None => src.origin.clone()
+                    };
+                    (path, origin, src.owner.clone())
+                };
+                let text = fs::read_to_string(&path)?;
+                let dir = path.parent().map(|p| p.to_path_buf());
+                let file = path.display().to_string();
+                self.sources.push(Source::new(&text, file, dir, origin, owner));
+            }
+            x => return syntax(&format!("unknown directive {}", x))
+        }
+        Ok(())
+    }
+    fn record(&mut self, entry: &Entry) -> Result<ResourceRecord, ZoneErrorKind> {
+        let src = self.sources.last_mut().unwrap();
+        let mut tokens = &entry.tokens[..];
+        let owner = if entry.leading_space {
+            src.owner.clone().ok_or(ZoneErrorKind::NoOwner)?
+        }
+        else {
+            let n = parse_name(&tokens[0].text, &src.origin)?;
+            tokens = &tokens[1..];
+            n
+        };
+        let mut ttl = None;
+        let mut class = None;
+        //TTL and class may come in either order
+        for _ in 0..2 {
+            match tokens.first() {
+                Some(t) if class.is_none() && Class::from_str(&t.text).is_ok() => {
+                    class = Class::from_str(&t.text).ok();
+                }
+                Some(t) if ttl.is_none() && parse_ttl(&t.text).is_some() => {
+                    ttl = parse_ttl(&t.text);
+                }
+                _ => break
+            }
+            tokens = &tokens[1..];
+        }
+        let rrtype = match tokens.first() {
+            Some(t) => Type::from_str(&t.text)
+                .or_else(|_| syntax(&format!("unknown type {}", t.text)))?,
+            None => return syntax("missing type")
+        };
+        if rrtype == Type::OPT {
+            return syntax("OPT records can't appear in a zone file");
+        }
+        let data = parse_rdata(rrtype, &tokens[1..], &src.origin)?;
+        if ttl.is_some() {
+            self.last_ttl = ttl;
+        }
+        if let Some(c) = class {
+            self.last_class = c;
+        }
+        src.owner = Some(owner.clone());
+        Ok(ResourceRecord {
+            rrname: owner,
+            multicast_unique: false,
+            rrclass: self.last_class,
+            ttl: ttl.or(self.default_ttl).or(self.last_ttl).ok_or(ZoneErrorKind::NoTtl)?,
+            data
+        })
+    }
+}
+
+impl Iterator for ZoneParser {
+    type Item = Result<ResourceRecord, ZoneError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = {
+                let src = self.sources.last_mut()?;
+                match src.entries.pop_front() {
+                    Some(e) => e,
+                    None => {
+                        let err = src.error.take().map(|(line, kind)| ZoneError {
+                            file: src.file.clone(),
+                            line,
+                            kind
+                        });
+                        if err.is_some() {
+                            self.sources.clear();
+                            return err.map(Err);
+                        }
+                        self.sources.pop();
+                        continue;
+                    }
+                }
+            };
+            let file = self.sources.last().unwrap().file.clone();
+            let is_directive = !entry.leading_space && !entry.tokens[0].quoted &&
+                entry.tokens[0].text.starts_with('$');
+            let result = if is_directive {
+                self.directive(&entry).map(|_| None)
+            }
+            else {
+                self.record(&entry).map(Some)
+            };
+            match result {
+                Ok(Some(rr)) => return Some(Ok(rr)),
+                Ok(None) => continue,
+                Err(kind) => {
+                    self.sources.clear();
+                    return Some(Err(ZoneError { file, line: entry.line, kind }));
+                }
+            }
+        }
+    }
+}
+
+struct Presentation<'a> {
+    rr: &'a ResourceRecord,
+    origin: Option<&'a Name>
+}
+
+impl<'a> fmt::Display for Presentation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.rr.fmt_presentation(f, self.origin)
+    }
+}
+
+/// Writes records as master file lines that `ZoneParser`, BIND and NSD can
+/// read back
+pub struct ZoneWriter<W: Write> {
+    out: W,
+    origin: Option<Name>
+}
+
+impl<W: Write> ZoneWriter<W> {
+    /// A writer that writes every name in absolute form
+    pub fn new(out: W) -> Self {
+        ZoneWriter { out, origin: None }
+    }
+    /// Write an `$ORIGIN` line; names within `origin` are written relative
+    /// to it from then on
+    pub fn set_origin(&mut self, origin: Name) -> io::Result<()> {
+        let origin = origin.qualify(&Name::root()).unwrap_or(origin);
+        writeln!(self.out, "$ORIGIN {}", origin.to_presentation(None))?;
+        self.origin = Some(origin);
+        Ok(())
+    }
+    /// Write a `$TTL` line
+    pub fn set_default_ttl(&mut self, ttl: u32) -> io::Result<()> {
+        writeln!(self.out, "$TTL {}", ttl)
+    }
+    pub fn write_record(&mut self, rr: &ResourceRecord) -> io::Result<()> {
+        writeln!(self.out, "{}", Presentation { rr, origin: self.origin.as_ref() })
+    }
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}